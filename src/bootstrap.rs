@@ -0,0 +1,84 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// A player's rating and rank across all bootstrap resamples.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BootstrapSummary {
+    pub median: f64,
+    /// 2.5th percentile of the rating.
+    pub lower: f64,
+    /// 97.5th percentile of the rating.
+    pub upper: f64,
+    /// Best plausible rank (2.5th percentile), starting at 1.
+    pub best_rank: usize,
+    /// Worst plausible rank (97.5th percentile).
+    pub worst_rank: usize,
+}
+
+/// Resamples `matches` with replacement `samples` times, rates each resample with `rate`
+/// and summarises every player's rating and rank distribution.
+///
+/// Resamples are rated in parallel. Each one draws from its own generator seeded from
+/// `seed` and its sample number, so results are reproducible regardless of scheduling.
+pub fn bootstrap<T, F>(
+    num_players: usize,
+    matches: &[T],
+    samples: usize,
+    seed: u64,
+    rate: F,
+) -> Vec<BootstrapSummary>
+where
+    T: Clone + Sync,
+    F: Fn(Vec<T>) -> Vec<f64> + Sync,
+{
+    let results: Vec<(Vec<f64>, Vec<usize>)> = (0..samples)
+        .into_par_iter()
+        .map(|sample| {
+            let mut rng = StdRng::seed_from_u64(sample_seed(seed, sample as u64));
+            let mut picks: Vec<usize> = (0..matches.len()).map(|_| rng.gen_range(0..matches.len())).collect();
+            // Keep the resampled matches in their original order for order-dependent algorithms.
+            picks.sort_unstable();
+            let resample: Vec<T> = picks.into_iter().map(|i| matches[i].clone()).collect();
+
+            let ratings = rate(resample);
+            let mut order: Vec<usize> = (0..num_players).collect();
+            order.sort_by(|&a, &b| ratings[b].total_cmp(&ratings[a]));
+            let mut ranks = vec![0; num_players];
+            for (rank, player) in order.into_iter().enumerate() {
+                ranks[player] = rank + 1;
+            }
+            (ratings, ranks)
+        })
+        .collect();
+
+    (0..num_players)
+        .map(|player| {
+            let mut ratings: Vec<f64> = results.iter().map(|(ratings, _)| ratings[player]).collect();
+            let mut ranks: Vec<usize> = results.iter().map(|(_, ranks)| ranks[player]).collect();
+            ratings.sort_by(f64::total_cmp);
+            ranks.sort_unstable();
+            BootstrapSummary {
+                median: ratings[percentile_index(ratings.len(), 0.5)],
+                lower: ratings[percentile_index(ratings.len(), 0.025)],
+                upper: ratings[percentile_index(ratings.len(), 0.975)],
+                best_rank: ranks[percentile_index(ranks.len(), 0.025)],
+                worst_rank: ranks[percentile_index(ranks.len(), 0.975)],
+            }
+        })
+        .collect()
+}
+
+/// Mixes `seed` and `sample` with a SplitMix64 step, so that nearby seeds don't share
+/// resamples the way `seed + sample` would (sample 1 of seed 42 being sample 0 of seed 43).
+fn sample_seed(seed: u64, sample: u64) -> u64 {
+    let mut z = (seed ^ sample.rotate_left(32)).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Index of the `p`-th quantile in a sorted slice of length `len`.
+fn percentile_index(len: usize, p: f64) -> usize {
+    ((len - 1) as f64 * p).round() as usize
+}
@@ -1,14 +1,12 @@
-use clap::{ValueEnum, Parser, Subcommand};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
-#[derive(Clone, Copy, ValueEnum, Debug)]
-pub enum VsResult {
-    /// Player1 wins
-    Gt, // Greater Than ('gt')
-    /// Player2 wins
-    Lt, // Less Than ('lt')
-    /// The match is a tie
-    Eq, // Equal ('eq')
-}
+use belo::elo_system::{MatchResult, SortBy};
+use belo::fit::FitModel;
+use belo::import::{ColumnMap, ImportFormat};
+use belo::leaderboard::ExportFormat;
+use belo::output::OutputFormat;
+use belo::rating::RatingAlgorithm;
 
 /// Main CLI entrypoint
 #[derive(Parser, Debug)]
@@ -19,6 +17,9 @@ pub enum VsResult {
     about = "Command line tool to manage Elo ratings",
 )]
 pub struct Cli {
+    /// Print each command's result as JSON on stdout, with messages on stderr
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
     #[command(subcommand)]
     pub command: Commands
 }
@@ -31,10 +32,13 @@ pub enum Commands {
     ///
     /// **Example**
     /// ```sh
-    /// belo init my_project
+    /// belo init my_project --algorithm trueskill
     /// ```
     Init {
         project_name: String,
+        /// Rating algorithm used for every game recorded in this project
+        #[arg(long, value_enum, default_value_t = RatingAlgorithm::Glicko2)]
+        algorithm: RatingAlgorithm,
     },
     /// Activates an existing project by name.
     Activate {
@@ -51,28 +55,274 @@ pub enum Commands {
         project_name: String,
     },
     /// Shows top N rated players
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo head 10 --sort conservative
+    /// belo head --bootstrap 1000 --seed 42
+    /// ```
     Head {
         count: Option<usize>,
+        /// rating = highest rating first, conservative = highest rating − 2·RD first
+        #[arg(long, value_enum, default_value_t = SortBy::Rating)]
+        sort: SortBy,
+        /// Resample the match history this many times and show bootstrap
+        /// intervals of each player's rating and rank instead
+        #[arg(long, value_name = "SAMPLES")]
+        bootstrap: Option<usize>,
+        /// Seed for the bootstrap resampling; the same seed gives the same intervals
+        #[arg(long, default_value_t = 0, requires = "bootstrap")]
+        seed: u64,
+        /// Rate each resample with a batch fit instead of replaying it with the project's algorithm
+        #[arg(long, value_enum, requires = "bootstrap")]
+        fit: Option<FitModel>,
+    },
+    /// Exports the full leaderboard for reports or other tools
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo export --to markdown --output standings.md
+    /// ```
+    Export {
+        /// Leaderboard format [default: csv, or json with `--format json`]
+        #[arg(long, value_name = "FORMAT", value_enum)]
+        to: Option<ExportFormat>,
+        /// File to write the leaderboard to (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// rating = highest rating first, conservative = highest rating − 2·RD first
+        #[arg(long, value_enum, default_value_t = SortBy::Rating)]
+        sort: SortBy,
     },
     /// Adds a new player to the system
     Add {
         id: String,
     },
+    /// Removes a player and every match they played
+    Remove {
+        id: String,
+    },
+    /// Renames a player
+    Rename {
+        old: String,
+        new: String,
+    },
+    /// Merges a player registered under a duplicate ID into another player
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo merge player_1 player1
+    /// ```
+    Merge {
+        duplicate: String,
+        into: String,
+    },
     /// Records a game between two players
     ///
     /// **Example**
     /// ```sh
     /// belo vs player1 gt player2
+    /// belo vs player1 gt:strong player2
+    /// belo vs player1 3-1 player2
     /// ```
     Vs {
         id1: String,
-        /// gt = Player1 wins, lt = Player2 wins, eq = Tie
-        #[arg(value_enum)]
-        result: VsResult,
+        /// gt = Player1 wins, lt = Player2 wins, eq = Tie; grade a win with
+        /// :strong or :weak (e.g. gt:strong), or give the score (e.g. 3-1)
+        result: MatchResult,
+        id2: String,
+    },
+    /// Records a series of games between two players in one go
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo series player1 player2 --results gt,gt,lt,eq,gt
+    /// belo series player1 player2 --results 3-1,2-2 --single-period
+    /// ```
+    Series {
+        id1: String,
+        id2: String,
+        /// Comma-separated results from player1's point of view, in the order they were
+        /// played; accepts everything `vs` does
+        #[arg(long, value_delimiter = ',', required = true)]
+        results: Vec<MatchResult>,
+        /// Rate the whole series as one rating period instead of game by game
+        #[arg(long)]
+        single_period: bool,
+    },
+    /// Records a game between two teams
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo team-vs player1,player2 gt player3,player4
+    /// ```
+    TeamVs {
+        /// Comma-separated player IDs of the first team
+        team1: String,
+        /// gt = Team 1 wins, lt = Team 2 wins, eq = Tie; also accepts graded
+        /// results such as gt:strong and scores such as 3-1
+        result: MatchResult,
+        /// Comma-separated player IDs of the second team
+        team2: String,
+    },
+    /// Records a free-for-all between two or more players
+    ///
+    /// Players are listed best first, `>` separating places and `=` joining ties.
+    /// Quote the ranking so the shell doesn't treat `>` as a redirection.
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo rank "player1 > player2 = player3 > player4"
+    /// ```
+    Rank {
+        #[arg(required = true, num_args = 1..)]
+        ranking: Vec<String>,
+    },
+    /// Imports games between two players from a CSV, JSON Lines or Chatbot Arena file
+    ///
+    /// Games are recorded in file order, exactly as if each had been recorded with `vs`.
+    /// If any row is invalid, nothing is imported.
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo import matches.csv --dry-run
+    /// belo import judgments.jsonl --player1-column model_a --player2-column model_b --create-players
+    /// belo import --from arena battles.jsonl --dedupe-by question_id --create-players
+    /// ```
+    Import {
+        path: PathBuf,
+        /// File format (default: from the file extension)
+        #[arg(long, value_name = "FORMAT", value_enum)]
+        from: Option<ImportFormat>,
+        #[command(flatten)]
+        columns: ColumnMap,
+        /// Add players that don't exist yet instead of rejecting their games
+        #[arg(long)]
+        create_players: bool,
+        /// Check the file and show what would be imported without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Fits all player strengths jointly from the match history
+    ///
+    /// Unlike the online ratings, the fit doesn't depend on the order in which games were recorded.
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo fit --model bradley-terry
+    /// ```
+    Fit {
+        /// Number of players to show (default: all)
+        count: Option<usize>,
+        #[arg(long, value_enum, default_value_t = FitModel::BradleyTerry)]
+        model: FitModel,
+    },
+    /// Predicts the outcome of a game between two players
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo predict player1 player2
+    /// ```
+    Predict {
+        id1: String,
         id2: String,
     },
     /// Shows the stats for a specific player
     Info {
         id: String,
     },
+    /// Reverts the last N recorded matches (default 1)
+    Undo {
+        count: Option<usize>,
+    },
+    /// Lists, corrects or deletes past matches
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo match edit 12 --result lt
+    /// ```
+    Match {
+        #[command(subcommand)]
+        action: MatchAction,
+    },
+    /// Rates games in rating periods instead of one by one
+    ///
+    /// While a period is open, recorded games update wins and losses right away, but ratings
+    /// change only when the period closes: every player is then rated over all their games
+    /// in the period at once, and players who didn't play become less certain (Glicko and
+    /// Glicko-2). Set `rating_period_days` to close periods automatically.
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo period start
+    /// belo config set rating_period_days 1
+    /// belo period close
+    /// ```
+    Period {
+        #[command(subcommand)]
+        action: PeriodAction,
+    },
+    /// Rebuilds all ratings from scratch by replaying the match history
+    ///
+    /// Use this after changing settings, or pass `--algorithm` to switch
+    /// the project to another rating algorithm.
+    Recompute {
+        #[arg(long, value_enum)]
+        algorithm: Option<RatingAlgorithm>,
+    },
+    /// Reads or changes the rating parameters of the active project
+    ///
+    /// **Example**
+    /// ```sh
+    /// belo config set tau 0.3
+    /// ```
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MatchAction {
+    /// Shows the last N recorded matches (default 10)
+    List {
+        count: Option<usize>,
+    },
+    /// Changes the result of a match and replays every later game
+    Edit {
+        id: u64,
+        /// gt = Player1 wins, lt = Player2 wins, eq = Tie; also accepts graded
+        /// results such as gt:strong and scores such as 3-1
+        #[arg(long)]
+        result: MatchResult,
+    },
+    /// Deletes a match and replays every later game
+    Delete {
+        id: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PeriodAction {
+    /// Opens a rating period; games recorded from now on are rated when it closes
+    Start,
+    /// Rates every player over the open period and starts the next one
+    Close,
+    /// Closes the open period and goes back to rating games as they are recorded
+    Stop,
+    /// Shows the open rating period
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Shows the value of a setting, or all settings if no key is given
+    Get {
+        key: Option<String>,
+    },
+    /// Changes the value of a setting
+    Set {
+        key: String,
+        value: f64,
+    },
 }
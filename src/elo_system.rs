@@ -1,12 +1,27 @@
 use clap::{ValueEnum};
-use anyhow::{Result};
+use rayon::prelude::*;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use prettytable::{Table, Row, Cell, format};
-use skillratings::{
-    glicko2::{glicko2, Glicko2Rating, Glicko2Config},
-    Outcomes,
+use skillratings::Outcomes;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
 };
-use std::collections::HashMap;
+
+use crate::bootstrap::{self, BootstrapSummary};
+use crate::error::BeloError;
+use crate::fit::{self, Fit, FitModel};
+use crate::import::{self, ImportedGame};
+use crate::leaderboard::LeaderboardRow;
+use crate::output::message;
+use crate::history::{place_result, Event, MatchRecord, RatingChange, RatingPeriod, Standing, TeamMember};
+use crate::rating::{Prediction, Rating, RatingAlgorithm};
+use crate::settings::Settings;
+
+const MILLISECONDS_PER_DAY: f64 = 86_400_000.0;
 
 /// Individual player data.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -15,43 +30,358 @@ pub struct Player {
     pub wins: u32,
     pub losses: u32,
     pub ties: u32,
-    rating: Glicko2Rating,
+    /// Points scored and conceded in games recorded with a score.
+    #[serde(default)]
+    pub points_for: u32,
+    #[serde(default)]
+    pub points_against: u32,
+    pub rating: Rating,
+    /// When the player's last game was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_played: Option<DateTime<Utc>>,
 }
 
 
-#[derive(Clone, Copy, ValueEnum, Debug)]
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum GameResult {
     Player1Wins,
     Player2Wins,
     Tie,
 }
 
+impl GameResult {
+    /// The outcome from player 1's point of view.
+    pub fn outcome(self) -> Outcomes {
+        match self {
+            GameResult::Player1Wins => Outcomes::WIN,
+            GameResult::Player2Wins => Outcomes::LOSS,
+            GameResult::Tie => Outcomes::DRAW,
+        }
+    }
+
+    /// The same result from player 2's point of view.
+    pub fn reversed(self) -> GameResult {
+        match self {
+            GameResult::Player1Wins => GameResult::Player2Wins,
+            GameResult::Player2Wins => GameResult::Player1Wins,
+            GameResult::Tie => GameResult::Tie,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            GameResult::Player1Wins => "gt",
+            GameResult::Player2Wins => "lt",
+            GameResult::Tie => "eq",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// How decisive a result was.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Margin {
+    #[default]
+    Normal,
+    /// A narrow or uncertain win, e.g. `gt:weak`.
+    Weak,
+    /// A clear win, e.g. `gt:strong`.
+    Strong,
+    /// The points scored by each side, e.g. `3-1`.
+    Score(u32, u32),
+}
+
+impl Margin {
+    pub fn is_normal(&self) -> bool {
+        *self == Margin::Normal
+    }
+
+    /// How many games' worth of rating change the result is worth, given the
+    /// `margin_weight` setting `w`: `1 + w` for a strong win, `1 − w` for a weak one and
+    /// `1 + w·ln(d)` for a score won by `d` points, so a one-point win counts as a normal game.
+    pub fn weight(self, margin_weight: f64) -> f64 {
+        match self {
+            Margin::Normal => 1.0,
+            Margin::Weak => 1.0 - margin_weight,
+            Margin::Strong => 1.0 + margin_weight,
+            Margin::Score(a, b) => 1.0 + margin_weight * f64::from(a.abs_diff(b).max(1)).ln(),
+        }
+    }
+}
+
+/// A result as written on the command line: `gt`, `lt` or `eq` (or `>`, `<`, `==`),
+/// optionally graded as in `gt:strong` or `lt:weak`, or a score such as `3-1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub result: GameResult,
+    #[serde(default, skip_serializing_if = "Margin::is_normal")]
+    pub margin: Margin,
+}
+
+impl From<GameResult> for MatchResult {
+    fn from(result: GameResult) -> Self {
+        MatchResult { result, margin: Margin::Normal }
+    }
+}
+
+impl FromStr for MatchResult {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid result '{}'. Use gt, lt or eq, a graded result such as gt:strong or lt:weak, \
+                 or a score such as 3-1.",
+                s
+            )
+        };
+
+        if let Some((score1, score2)) = s.split_once('-') {
+            let score1: u32 = score1.trim().parse().map_err(|_| invalid())?;
+            let score2: u32 = score2.trim().parse().map_err(|_| invalid())?;
+            let result = match score1.cmp(&score2) {
+                std::cmp::Ordering::Greater => GameResult::Player1Wins,
+                std::cmp::Ordering::Less => GameResult::Player2Wins,
+                std::cmp::Ordering::Equal => GameResult::Tie,
+            };
+            return Ok(MatchResult { result, margin: Margin::Score(score1, score2) });
+        }
+
+        let (symbol, grade) = match s.split_once(':') {
+            Some((symbol, grade)) => (symbol, Some(grade)),
+            None => (s, None),
+        };
+        let result = match symbol {
+            "gt" | ">" => GameResult::Player1Wins,
+            "lt" | "<" => GameResult::Player2Wins,
+            "eq" | "==" => GameResult::Tie,
+            _ => return Err(invalid()),
+        };
+        let margin = match grade {
+            None => Margin::Normal,
+            Some("strong") => Margin::Strong,
+            Some("weak") => Margin::Weak,
+            Some(_) => return Err(invalid()),
+        };
+        if result == GameResult::Tie && !margin.is_normal() {
+            return Err(format!("Invalid result '{}'. A tie cannot be graded.", s));
+        }
+        Ok(MatchResult { result, margin })
+    }
+}
+
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.margin {
+            Margin::Normal => write!(f, "{}", self.result),
+            Margin::Weak => write!(f, "{}:weak", self.result),
+            Margin::Strong => write!(f, "{}:strong", self.result),
+            Margin::Score(score1, score2) => write!(f, "{}-{}", score1, score2),
+        }
+    }
+}
+
+
+/// Order in which players are listed on the leaderboard.
+#[derive(Clone, Copy, ValueEnum, Debug, Default)]
+pub enum SortBy {
+    /// Highest rating first
+    #[default]
+    Rating,
+    /// Highest rating − 2·RD first, so lightly-played players don't top the board
+    Conservative,
+}
+
+
+/// An `EloSystem` with the settings and history it normally stores in files of their own.
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    #[serde(flatten)]
+    system: &'a EloSystem,
+    settings: &'a Settings,
+    history: &'a [MatchRecord],
+}
+
+#[derive(Deserialize)]
+struct OwnedSnapshot {
+    #[serde(flatten)]
+    system: EloSystem,
+    settings: Option<Settings>,
+    #[serde(default)]
+    history: Vec<MatchRecord>,
+}
+
+/// A player's strength from a batch fit next to their online rating.
+#[derive(Debug, Clone, Serialize)]
+pub struct FittedRating {
+    /// Rank by fitted strength, starting at 1.
+    pub rank: usize,
+    pub id: String,
+    pub fitted: f64,
+    pub online_rating: f64,
+    pub online_rank: usize,
+}
+
+/// A player's bootstrap intervals, ranked by median rating.
+#[derive(Debug, Clone, Serialize)]
+pub struct BootstrapRating {
+    pub rank: usize,
+    pub id: String,
+    #[serde(flatten)]
+    pub summary: BootstrapSummary,
+}
+
+/// Whether games are currently collected in a rating period.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodStatus {
+    pub open: Option<RatingPeriod>,
+    /// Matches recorded in the open period that will be rated when it closes.
+    pub matches_waiting: usize,
+    /// When the open period runs out, if periods roll over automatically.
+    pub closes_at: Option<DateTime<Utc>>,
+    /// Number of periods closed so far.
+    pub closed: usize,
+}
+
 
 /// A container for storing all relevant Elo system data.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EloSystem {
+    /// Projects created before algorithms were selectable always used Glicko-2.
+    #[serde(default)]
+    algorithm: RatingAlgorithm,
     players: HashMap<String, Player>,
+    /// Stored separately in the project's `settings.json`.
+    #[serde(skip)]
+    settings: Settings,
+    /// Stored separately in the project's `history.jsonl`.
+    #[serde(skip)]
+    history: Vec<MatchRecord>,
+    /// How many leading records of `history` are stored unchanged in `history.jsonl`, or
+    /// `None` when older records changed and the file has to be rewritten.
+    #[serde(skip)]
+    saved_matches: Option<usize>,
+    /// Rating periods, oldest first. While the last one is open, new games are rated only
+    /// when it closes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    periods: Vec<RatingPeriod>,
+}
+
+impl Default for EloSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EloSystem {
-    /// Create a new Elo system data structure.
+    /// Create a new Elo system data structure using the default algorithm (Glicko-2).
     pub fn new() -> Self {
+        Self::with_algorithm(RatingAlgorithm::default())
+    }
+
+    /// Create a new Elo system data structure using the given rating algorithm.
+    pub fn with_algorithm(algorithm: RatingAlgorithm) -> Self {
         Self {
+            algorithm,
             players: HashMap::new(),
+            settings: Settings::new(algorithm),
+            history: Vec::new(),
+            saved_matches: None,
+            periods: Vec::new(),
         }
     }
 
-    /// Adds a new player to the system with a default rating of 1000.
+    pub fn algorithm(&self) -> RatingAlgorithm {
+        self.algorithm
+    }
+
+    /// Switches to another rating algorithm, resetting the default rating and deviation to
+    /// its scale and keeping every other setting.
+    ///
+    /// Existing ratings are left untouched until the next `recompute`.
+    pub fn set_algorithm(&mut self, algorithm: RatingAlgorithm) {
+        self.algorithm = algorithm;
+        self.settings.switch_algorithm(algorithm);
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Replaces the rating parameters used for new players and future games.
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+    }
+
+    /// Every recorded game, oldest first.
+    pub fn history(&self) -> &[MatchRecord] {
+        &self.history
+    }
+
+    /// Replaces the match history, e.g. after loading it from disk.
+    pub fn set_history(&mut self, history: Vec<MatchRecord>) {
+        self.history = history;
+        self.saved_matches = None;
+    }
+
+    /// Matches recorded since the history was last marked as saved, or `None` when older
+    /// matches changed too and the whole history has to be written again.
+    pub fn unsaved_history(&self) -> Option<&[MatchRecord]> {
+        self.saved_matches.map(|saved| &self.history[saved..])
+    }
+
+    /// Marks the whole history as stored, so that only matches recorded from now on are unsaved.
+    pub fn mark_history_saved(&mut self) {
+        self.saved_matches = Some(self.history.len());
+    }
+
+    /// The history, for changes to matches that may already be saved.
+    fn history_mut(&mut self) -> &mut Vec<MatchRecord> {
+        self.saved_matches = None;
+        &mut self.history
+    }
+
+    /// The whole system, including its settings and match history, as one JSON document.
+    pub fn to_json(&self) -> Result<String> {
+        let snapshot = Snapshot { system: self, settings: &self.settings, history: &self.history };
+        Ok(serde_json::to_string(&snapshot)?)
+    }
+
+    /// Restores a system saved with `to_json`.
+    ///
+    /// A project's `elo_data.json` on its own can be read too; it gets the algorithm's
+    /// default settings and no match history.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let snapshot: OwnedSnapshot = serde_json::from_str(json).with_context(|| "Failed to parse Elo system")?;
+        let mut system = snapshot.system;
+        system.settings = snapshot.settings.unwrap_or_else(|| Settings::new(system.algorithm));
+        system.history = snapshot.history;
+        Ok(system)
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    /// Adds a new player to the system with the algorithm's default rating.
     pub fn add_player(&mut self, id: &str) -> Result<()> {
         if id.trim().is_empty() {
-            println!("Player ID cannot be empty.");
-            return Ok(());
+            return Err(BeloError::EmptyId.into());
         }
         if self.players.contains_key(id) {
-            println!("Player with ID '{}' already exists.", id);
-            return Ok(());
+            return Err(BeloError::DuplicatePlayer(id.to_string()).into());
         }
 
+        self.insert_player(id);
+        message!("Player with ID '{}' added.", id);
+        Ok(())
+    }
+
+    fn insert_player(&mut self, id: &str) {
         self.players.insert(
             id.to_string(),
             Player {
@@ -59,71 +389,801 @@ impl EloSystem {
                 wins: 0,
                 losses: 0,
                 ties: 0,
-                rating: Glicko2Rating::new(),
+                points_for: 0,
+                points_against: 0,
+                rating: self.algorithm.initial_rating(&self.settings),
+                last_played: None,
             },
         );
+    }
 
-        println!("Player with ID '{}' added.", id);
+    /// Removes a player together with every match they played, replaying the
+    /// remaining history so their opponents' stats and ratings stay consistent.
+    pub fn remove_player(&mut self, id: &str) -> Result<()> {
+        let games = self.games_played(id)?;
+        if games > 0 {
+            self.check_history()?;
+        }
+
+        self.players.remove(id);
+        let matches = self.history.len();
+        // Team games and free-for-alls go on without the player if enough players remain.
+        self.history_mut().retain_mut(|record| record.event.remove_player(id));
+        let removed = matches - self.history.len();
+        if games > 0 {
+            self.replay_history();
+        }
+
+        message!("Player with ID '{}' removed along with {} matches.", id, removed);
         Ok(())
     }
 
-    /// Handle a matchup between two players.
-    pub fn record_game(&mut self, id1: &str, result: GameResult, id2: &str) -> Result<()> {
-        if !self.players.contains_key(id1) || !self.players.contains_key(id2) {
-            println!("Both players must exist before recording a game.");
+    /// Renames a player, including in every match they played. Renaming a player to their
+    /// own name changes nothing.
+    pub fn rename_player(&mut self, old: &str, new: &str) -> Result<()> {
+        if !self.players.contains_key(old) {
+            return Err(BeloError::UnknownPlayer(old.to_string()).into());
+        }
+        if new.trim().is_empty() {
+            return Err(BeloError::EmptyId.into());
+        }
+        if new == old {
+            message!("Player '{}' already has that name.", old);
             return Ok(());
         }
+        if self.players.contains_key(new) {
+            return Err(BeloError::DuplicatePlayer(new.to_string()).into());
+        }
+        let mut player = self.players.remove(old).unwrap();
 
+        player.id = new.to_string();
+        self.players.insert(new.to_string(), player);
+        for record in self.history_mut().iter_mut() {
+            for player in record.event.players_mut() {
+                if player == old {
+                    *player = new.to_string();
+                }
+            }
+        }
+
+        message!("Player '{}' renamed to '{}'.", old, new);
+        Ok(())
+    }
+
+    /// Merges a player registered under a duplicate ID into another player.
+    ///
+    /// The duplicate's matches are reassigned, games between the two IDs are dropped
+    /// (in team games and free-for-alls only the merged player's own entry is kept),
+    /// and the history is replayed so the merged player is rated as a single player.
+    pub fn merge_players(&mut self, duplicate: &str, into: &str) -> Result<()> {
+        let games = self.games_played(duplicate)?;
+        self.games_played(into)?;
+        if duplicate == into {
+            return Err(BeloError::SelfMatch.into());
+        }
+        if games > 0 {
+            self.check_history()?;
+        }
+
+        self.players.remove(duplicate);
+        let mut dropped = 0;
+        self.history_mut().retain_mut(|record| {
+            let players = record.event.players();
+            if players.iter().any(|p| *p == into) && players.iter().any(|p| *p == duplicate) {
+                dropped += 1;
+                if !record.event.remove_player(duplicate) {
+                    return false;
+                }
+            }
+            for player in record.event.players_mut() {
+                if player == duplicate {
+                    *player = into.to_string();
+                }
+            }
+            true
+        });
+        if games > 0 {
+            self.replay_history();
+        }
+
+        message!(
+            "Player '{}' merged into '{}' ({} matches with both of them dropped or trimmed).",
+            duplicate, into, dropped
+        );
+        Ok(())
+    }
+
+    fn games_played(&self, id: &str) -> Result<u32> {
+        self.players
+            .get(id)
+            .map(|p| p.wins + p.losses + p.ties)
+            .ok_or_else(|| BeloError::UnknownPlayer(id.to_string()).into())
+    }
+
+    /// Handle a matchup between two players.
+    pub fn record_game(&mut self, id1: &str, result: MatchResult, id2: &str) -> Result<()> {
+        for id in [id1, id2] {
+            if !self.players.contains_key(id) {
+                return Err(BeloError::UnknownPlayer(id.to_string()).into());
+            }
+        }
+        if id1 == id2 {
+            return Err(BeloError::SelfMatch.into());
+        }
+
+        let record = self.push_game(id1, result, id2);
+        message!("Game recorded!");
+        print_rating_changes(record);
+        Ok(())
+    }
+
+    /// Records a series of games between two players at once.
+    ///
+    /// By default every game is rated and stored on its own, exactly as if it had been
+    /// recorded with `record_game`. With `single_period`, the series is stored as one match
+    /// and rated as a single rating period against the ratings both players started with.
+    /// Margins then still count towards points, but every game is rated as a plain result.
+    pub fn record_series(&mut self, id1: &str, id2: &str, results: &[MatchResult], single_period: bool) -> Result<()> {
+        if results.is_empty() {
+            return Err(anyhow!("A series needs at least one game."));
+        }
+        for id in [id1, id2] {
+            if !self.players.contains_key(id) {
+                return Err(BeloError::UnknownPlayer(id.to_string()).into());
+            }
+        }
         if id1 == id2 {
-            println!("A player cannot play against themselves.");
+            return Err(BeloError::SelfMatch.into());
+        }
+
+        let before = [self.players[id1].rating, self.players[id2].rating];
+        if single_period {
+            let event = Event::Series {
+                player1: id1.to_string(),
+                player2: id2.to_string(),
+                games: results.to_vec(),
+                player1_rating: self.unchanged(id1),
+                player2_rating: self.unchanged(id2),
+            };
+            self.push_event(event, Utc::now());
+        } else {
+            for &result in results {
+                self.push_game(id1, result, id2);
+            }
+        }
+
+        let count = |result: GameResult| results.iter().filter(|r| r.result == result).count();
+        message!(
+            "Series recorded: {} won {}, lost {} and tied {} of {} games against {}{}.",
+            id1,
+            count(GameResult::Player1Wins),
+            count(GameResult::Player2Wins),
+            count(GameResult::Tie),
+            results.len(),
+            id2,
+            if single_period { ", rated as one rating period" } else { "" }
+        );
+        if let Some(period) = self.open_period() {
+            print_deferred(period.id);
             return Ok(());
         }
+        for (id, before) in [id1, id2].into_iter().zip(before) {
+            let after = self.players[id].rating;
+            message!(
+                "Player {} ELO: {:.2} -> {:.2} (Δ{:.2})",
+                id,
+                before.rating,
+                after.rating,
+                after.rating - before.rating
+            );
+        }
+        Ok(())
+    }
 
-        // Update player stats using a helper function
-        self.update_stats(id1, result, true);
-        self.update_stats(id2, result, false);
+    /// Records many games at once, e.g. the judgments of a whole evaluation run.
+    ///
+    /// Every game is checked before any is recorded, so a bad one leaves the system untouched.
+    /// Returns how much each game moved both players' ratings, which is zero for games
+    /// deferred to an open rating period.
+    pub fn record_games(&mut self, games: &[(String, MatchResult, String)]) -> Result<Vec<(f64, f64)>> {
+        for (id1, _, id2) in games {
+            for id in [id1, id2] {
+                if !self.players.contains_key(id) {
+                    return Err(BeloError::UnknownPlayer(id.to_string()).into());
+                }
+            }
+            if id1 == id2 {
+                return Err(BeloError::SelfMatch.into());
+            }
+        }
 
-        // Handle Elo rating updates
-        let config = Glicko2Config::new();
-        let player1_old_elo = self.players[id1].rating.rating;
-        let player2_old_elo = self.players[id2].rating.rating;
+        let deltas = games
+            .iter()
+            .map(|(id1, result, id2)| {
+                let changes = self.push_game(id1, *result, id2).event.rating_changes();
+                let delta = |change: &RatingChange| change.after.rating - change.before.rating;
+                (delta(changes[0].1), delta(changes[1].1))
+            })
+            .collect();
+        message!("Games recorded: {}.", games.len());
+        if let Some(period) = self.open_period() {
+            print_deferred(period.id);
+        }
+        Ok(deltas)
+    }
 
-        let outcome = match result {
-            GameResult::Player1Wins => Outcomes::WIN,
-            GameResult::Player2Wins => Outcomes::LOSS,
-            GameResult::Tie => Outcomes::DRAW,
+    /// Records a game between two teams. Every member gets the team's win, loss or tie.
+    pub fn record_team_game(&mut self, team1: &[String], result: MatchResult, team2: &[String]) -> Result<()> {
+        if team1.is_empty() || team2.is_empty() {
+            return Err(anyhow!("Both teams need at least one player."));
+        }
+        let mut members: Vec<&String> = Vec::new();
+        for id in team1.iter().chain(team2) {
+            if !self.players.contains_key(id) {
+                return Err(BeloError::UnknownPlayer(id.to_string()).into());
+            }
+            if members.contains(&id) {
+                return Err(anyhow!("Player '{}' appears more than once in the game.", id));
+            }
+            members.push(id);
+        }
+
+        let team = |ids: &[String]| -> Vec<TeamMember> {
+            ids.iter().map(|id| TeamMember { player: id.clone(), rating: self.unchanged(id) }).collect()
+        };
+        let event = Event::Teams {
+            team1: team(team1),
+            result: result.result,
+            margin: result.margin,
+            team2: team(team2),
+        };
+        let record = self.push_event(event, Utc::now());
+
+        message!("Team game recorded: {}", record.event);
+        print_rating_changes(record);
+        Ok(())
+    }
+
+    /// Records a free-for-all with a full finishing order.
+    ///
+    /// `ranking` lists the finishers best first; players in the same group tied.
+    /// Every player is counted as having played each opponent once, so the winner of a
+    /// four-player event gets three wins.
+    pub fn record_ranking(&mut self, ranking: &[Vec<String>]) -> Result<()> {
+        let mut standings: Vec<Standing> = Vec::new();
+        for group in ranking {
+            // Tied players share the best place of their group, e.g. a > b = c > d is 1, 2, 2, 4.
+            let place = standings.len() + 1;
+            for id in group {
+                let player = self
+                    .players
+                    .get(id)
+                    .ok_or_else(|| BeloError::UnknownPlayer(id.to_string()))?;
+                if standings.iter().any(|s| &s.player == id) {
+                    return Err(anyhow!("Player '{}' appears more than once in the ranking.", id));
+                }
+                standings.push(Standing {
+                    player: id.clone(),
+                    place,
+                    rating: RatingChange { before: player.rating, after: player.rating },
+                });
+            }
+        }
+        if standings.len() < 2 {
+            return Err(anyhow!("A ranking needs at least two players."));
+        }
+
+        let record = self.push_event(Event::Ranking { standings }, Utc::now());
+
+        message!("Ranking recorded: {}", record.event);
+        print_rating_changes(record);
+        Ok(())
+    }
+
+    /// Checks that every imported game can be recorded, returning the players that
+    /// would have to be created for it, in order of appearance.
+    pub fn check_import(&self, games: &[ImportedGame], create_players: bool) -> Result<Vec<String>> {
+        let mut new_players: Vec<String> = Vec::new();
+        let mut seen = HashSet::new();
+        let mut errors = Vec::new();
+        for game in games {
+            if game.player1 == game.player2 {
+                errors.push(format!("line {}: A player cannot play against themselves.", game.line));
+                continue;
+            }
+            for id in [&game.player1, &game.player2] {
+                if self.players.contains_key(id) || seen.contains(id) {
+                    continue;
+                }
+                if create_players {
+                    seen.insert(id);
+                    new_players.push(id.clone());
+                } else {
+                    errors.push(format!(
+                        "line {}: Player with ID '{}' not found; pass --create-players to add unknown players.",
+                        game.line, id
+                    ));
+                }
+            }
+        }
+        import::reject_invalid(errors)?;
+        Ok(new_players)
+    }
+
+    /// Records imported games in file order, all or nothing.
+    ///
+    /// Returns the players created for the import.
+    pub fn import_games(&mut self, games: &[ImportedGame], create_players: bool) -> Result<Vec<String>> {
+        let new_players = self.check_import(games, create_players)?;
+        for id in &new_players {
+            self.insert_player(id);
+        }
+
+        let now = Utc::now();
+        for game in games {
+            let event = Event::Duel {
+                player1: game.player1.clone(),
+                result: game.result.result,
+                margin: game.result.margin,
+                player2: game.player2.clone(),
+                weight: game.weight,
+                player1_rating: self.unchanged(&game.player1),
+                player2_rating: self.unchanged(&game.player2),
+            };
+            self.push_event(event, game.played_at.unwrap_or(now));
+            let record = self.history.last_mut().unwrap();
+            record.source_id = game.source_id.clone();
+            record.both_bad = game.both_bad;
+        }
+        Ok(new_players)
+    }
+
+    /// Drops imported games whose source ID is already in the history or appeared earlier
+    /// in the import, returning the games to keep and the number dropped.
+    pub fn drop_duplicates(&self, games: Vec<ImportedGame>) -> (Vec<ImportedGame>, usize) {
+        let mut seen: HashSet<String> =
+            self.history.iter().filter_map(|record| record.source_id.clone()).collect();
+        let count = games.len();
+        let games: Vec<ImportedGame> = games
+            .into_iter()
+            .filter(|game| game.source_id.as_ref().is_none_or(|id| seen.insert(id.clone())))
+            .collect();
+        let dropped = count - games.len();
+        (games, dropped)
+    }
+
+    /// Records a game between two existing players.
+    fn push_game(&mut self, id1: &str, result: MatchResult, id2: &str) -> &MatchRecord {
+        let event = Event::Duel {
+            player1: id1.to_string(),
+            result: result.result,
+            margin: result.margin,
+            player2: id2.to_string(),
+            weight: 1.0,
+            player1_rating: self.unchanged(id1),
+            player2_rating: self.unchanged(id2),
         };
+        self.push_event(event, Utc::now())
+    }
+
+    /// Adds a new event to the history, updating its players' stats.
+    ///
+    /// The event is rated right away unless a rating period is open, in which case it is
+    /// rated together with the rest of the period when the period closes.
+    fn push_event(&mut self, mut event: Event, played_at: DateTime<Utc>) -> &MatchRecord {
+        self.roll_periods(played_at);
+        let period = self.open_period().map(|period| period.id);
+        self.apply_record(&mut event, period, played_at);
+        self.history.push(MatchRecord {
+            id: self.next_match_id(),
+            played_at,
+            period,
+            source_id: None,
+            both_bad: false,
+            event,
+        });
+        self.history.last().unwrap()
+    }
+
+    /// A player's current rating, as the not yet rated change of a new event.
+    fn unchanged(&self, id: &str) -> RatingChange {
+        let rating = self.players[id].rating;
+        RatingChange { before: rating, after: rating }
+    }
+
+    fn next_match_id(&self) -> u64 {
+        self.history.last().map_or(1, |record| record.id + 1)
+    }
+
+    /// The rating period new games are recorded in, if the project uses rating periods.
+    pub fn open_period(&self) -> Option<&RatingPeriod> {
+        self.periods.last().filter(|period| period.closed_at.is_none())
+    }
 
-        let (new_player1, new_player2) = glicko2(
-            &self.players[id1].rating,
-            &self.players[id2].rating,
-            &outcome,
-            &config,
+    /// Starts using rating periods: games recorded from now on are rated together
+    /// when the period closes.
+    pub fn start_period(&mut self) -> Result<&RatingPeriod> {
+        if let Some(period) = self.open_period() {
+            return Err(anyhow!("Rating period #{} is already open.", period.id));
+        }
+        self.periods.push(RatingPeriod {
+            id: self.periods.last().map_or(1, |period| period.id + 1),
+            started_at: Utc::now(),
+            closed_at: None,
+        });
+        Ok(self.periods.last().unwrap())
+    }
+
+    /// Closes the open rating period and rates every player over its games.
+    ///
+    /// Periods that already ran out under `rating_period_days` are closed first. Unless
+    /// `stop` is set, the next period starts right away. Returns the closed period and
+    /// the number of matches rated in it.
+    pub fn close_period(&mut self, stop: bool) -> Result<(RatingPeriod, usize)> {
+        let now = Utc::now();
+        self.roll_periods(now);
+        if self.open_period().is_none() {
+            return Err(anyhow!("No rating period is open. Start one with 'belo period start'."));
+        }
+        self.check_history()?;
+
+        let period = self.end_period(now, !stop);
+        let matches = self.history.iter().filter(|record| record.period == Some(period.id)).count();
+        Ok((period, matches))
+    }
+
+    /// Closes every open period that ran out before `now`, when periods roll over automatically.
+    fn roll_periods(&mut self, now: DateTime<Utc>) {
+        let Some(length) = self.period_length() else {
+            return;
+        };
+        while let Some(period) = self.open_period() {
+            let end = period.started_at + length;
+            if end > now {
+                break;
+            }
+            self.end_period(end, true);
+        }
+    }
+
+    /// How long a rating period lasts before the next one starts, if they roll over automatically.
+    fn period_length(&self) -> Option<Duration> {
+        let days = self.settings.rating_period_days;
+        (days > 0.0).then(|| Duration::milliseconds((days * MILLISECONDS_PER_DAY) as i64))
+    }
+
+    /// Closes the open period at `closed_at`, rates its games and optionally starts the next one.
+    fn end_period(&mut self, closed_at: DateTime<Utc>, start_next: bool) -> RatingPeriod {
+        let period = self.periods.last_mut().expect("a rating period is open");
+        period.closed_at = Some(closed_at);
+        let period = period.clone();
+
+        let mut history = std::mem::take(self.history_mut());
+        self.rate_period_records(&mut history, period.id);
+        self.history = history;
+
+        if start_next {
+            self.periods.push(RatingPeriod { id: period.id + 1, started_at: closed_at, closed_at: None });
+        }
+        period
+    }
+
+    /// Predicts the outcome of a game between two players from their current ratings.
+    pub fn predict(&self, id1: &str, id2: &str) -> Result<Prediction> {
+        if id1 == id2 {
+            return Err(BeloError::SelfMatch.into());
+        }
+        let player1 = self.players.get(id1).ok_or_else(|| BeloError::UnknownPlayer(id1.to_string()))?;
+        let player2 = self.players.get(id2).ok_or_else(|| BeloError::UnknownPlayer(id2.to_string()))?;
+        let now = Utc::now();
+        Ok(self.algorithm.predict(
+            &self.current_rating(player1, now),
+            &self.current_rating(player2, now),
+            &self.settings,
+        ))
+    }
+
+    /// Rebuilds every player's stats and rating from scratch by replaying the match history
+    /// with the current algorithm and settings.
+    ///
+    /// Returns the number of replayed matches.
+    pub fn recompute(&mut self) -> Result<usize> {
+        self.check_history()?;
+        self.replay_history();
+        Ok(self.history.len())
+    }
+
+    /// Removes the last `n` matches from the history and replays the rest.
+    ///
+    /// Returns the removed matches, oldest first.
+    pub fn undo(&mut self, n: usize) -> Result<Vec<MatchRecord>> {
+        if n > self.history.len() {
+            return Err(anyhow!(
+                "Cannot undo {} matches, only {} have been recorded.",
+                n,
+                self.history.len()
+            ));
+        }
+        self.check_history()?;
+
+        let kept = self.history.len() - n;
+        let removed = self.history_mut().split_off(kept);
+        self.replay_history();
+        Ok(removed)
+    }
+
+    /// Changes the result of a past match and replays every game after it.
+    pub fn edit_match(&mut self, id: u64, result: MatchResult) -> Result<()> {
+        let index = self.match_index(id)?;
+        if self.history[index].event.result_mut().is_none() {
+            return Err(anyhow!("Match #{} has no single result to edit; delete it and record it again.", id));
+        }
+        self.check_history()?;
+
+        let (recorded, margin) = self.history_mut()[index].event.result_mut().unwrap();
+        *recorded = result.result;
+        *margin = result.margin;
+        self.replay_history();
+        Ok(())
+    }
+
+    /// Deletes a past match and replays every game after it.
+    pub fn delete_match(&mut self, id: u64) -> Result<MatchRecord> {
+        let index = self.match_index(id)?;
+        self.check_history()?;
+
+        let removed = self.history_mut().remove(index);
+        self.replay_history();
+        Ok(removed)
+    }
+
+    fn match_index(&self, id: u64) -> Result<usize> {
+        self.history
+            .iter()
+            .position(|record| record.id == id)
+            .ok_or_else(|| BeloError::UnknownMatch(id).into())
+    }
+
+    /// Makes sure the history can be replayed without losing any recorded game.
+    fn check_history(&self) -> Result<()> {
+        let games_played: u32 = self.players.values().map(|p| p.wins + p.losses + p.ties).sum();
+        let recorded: usize = self.history.iter().map(|r| r.event.results_recorded()).sum();
+        if games_played as usize != recorded {
+            return Err(anyhow!(
+                "The match history doesn't cover every recorded game, so replaying it would lose games. \
+                 Projects created before match history was introduced cannot be replayed."
+            ));
+        }
+        if let Some(record) = self
+            .history
+            .iter()
+            .find(|r| r.event.players().iter().any(|id| !self.players.contains_key(*id)))
+        {
+            return Err(anyhow!("Match #{} refers to a player that doesn't exist.", record.id));
+        }
+        Ok(())
+    }
+
+    /// Resets every player and replays the whole history, refreshing the
+    /// before/after ratings stored in each record.
+    fn replay_history(&mut self) {
+        let initial = self.algorithm.initial_rating(&self.settings);
+        for player in self.players.values_mut() {
+            player.wins = 0;
+            player.losses = 0;
+            player.ties = 0;
+            player.points_for = 0;
+            player.points_against = 0;
+            player.rating = initial;
+            player.last_played = None;
+        }
+
+        // Closed periods are rated where they ended: before the first event of a later period,
+        // or before a game rated on its own that was recorded after the period closed.
+        let periods = self.periods.clone();
+        let mut closed = periods.iter().filter(|period| period.closed_at.is_some()).peekable();
+        let mut history = std::mem::take(self.history_mut());
+        for i in 0..history.len() {
+            let (period, played_at) = (history[i].period, history[i].played_at);
+            while let Some(ended) = closed.next_if(|closed| match period {
+                Some(id) => closed.id < id,
+                None => closed.closed_at <= Some(played_at),
+            }) {
+                self.rate_period_records(&mut history, ended.id);
+            }
+
+            self.apply_record(&mut history[i].event, period, played_at);
+        }
+        for ended in closed {
+            self.rate_period_records(&mut history, ended.id);
+        }
+        self.history = history;
+    }
+
+    /// Applies an event played at `played_at`, rating it right away unless it belongs to a
+    /// rating period.
+    fn apply_record(&mut self, event: &mut Event, period: Option<u64>, played_at: DateTime<Utc>) {
+        match period {
+            Some(_) => self.apply_stats(event),
+            None => {
+                // Players coming back from a break are rated with the uncertainty they gained meanwhile.
+                for id in event.players() {
+                    let rating = self.current_rating(&self.players[id], played_at);
+                    self.players.get_mut(id).unwrap().rating = rating;
+                }
+                self.apply_event(event);
+            }
+        }
+        for id in event.players() {
+            self.players.get_mut(id).unwrap().last_played = Some(played_at);
+        }
+    }
+
+    /// A player's rating at `at`, with the deviation grown by the `deviation_decay` setting
+    /// for the time since their last game.
+    fn current_rating(&self, player: &Player, at: DateTime<Utc>) -> Rating {
+        match player.last_played {
+            Some(last_played) if self.algorithm.has_deviation() => {
+                let days = (at - last_played).num_milliseconds() as f64 / MILLISECONDS_PER_DAY;
+                player.rating.decayed(days, self.settings.deviation_decay, self.settings.default_deviation)
+            }
+            _ => player.rating,
+        }
+    }
+
+    /// Updates the stats and ratings of everyone in the event, storing their rating changes in it.
+    fn apply_event(&mut self, event: &mut Event) {
+        self.apply_stats(event);
+        self.rate_event(event);
+    }
+
+    /// Adds the event's wins, losses, ties and points to its players' stats.
+    fn apply_stats(&mut self, event: &Event) {
+        match event {
+            Event::Duel { player1, result, margin, player2, .. } => {
+                self.update_stats(player1, *result, true);
+                self.update_stats(player2, *result, false);
+                self.add_points(player1, *margin, true);
+                self.add_points(player2, *margin, false);
+            }
+            Event::Series { player1, player2, games, .. } => {
+                for game in games {
+                    self.update_stats(player1, game.result, true);
+                    self.update_stats(player2, game.result, false);
+                    self.add_points(player1, game.margin, true);
+                    self.add_points(player2, game.margin, false);
+                }
+            }
+            Event::Teams { team1, result, margin, team2 } => {
+                for (team, is_team1) in [(team1, true), (team2, false)] {
+                    for member in team {
+                        self.update_stats(&member.player, *result, is_team1);
+                        self.add_points(&member.player, *margin, is_team1);
+                    }
+                }
+            }
+            Event::Ranking { standings } => {
+                for standing in standings {
+                    for other in standings {
+                        if other.player != standing.player {
+                            self.update_stats(&standing.player, place_result(standing.place, other.place), true);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Rates the event on its own, storing everyone's rating changes in it.
+    fn rate_event(&mut self, event: &mut Event) {
+        match event {
+            Event::Duel { player1, result, margin, player2, weight, player1_rating, player2_rating } => {
+                let result = MatchResult { result: *result, margin: *margin };
+                (*player1_rating, *player2_rating) = self.rate_game(player1, result, player2, *weight);
+            }
+            Event::Series { player1, player2, games, player1_rating, player2_rating } => {
+                let before1 = self.players[player1.as_str()].rating;
+                let before2 = self.players[player2.as_str()].rating;
+                let results1: Vec<_> = games.iter().map(|g| (before2, g.result.outcome())).collect();
+                let results2: Vec<_> = games.iter().map(|g| (before1, g.result.reversed().outcome())).collect();
+                let after1 = self.algorithm.rate_period(&before1, &results1, &self.settings);
+                let after2 = self.algorithm.rate_period(&before2, &results2, &self.settings);
+
+                self.players.get_mut(player1.as_str()).unwrap().rating = after1;
+                self.players.get_mut(player2.as_str()).unwrap().rating = after2;
+                *player1_rating = RatingChange { before: before1, after: after1 };
+                *player2_rating = RatingChange { before: before2, after: after2 };
+            }
+            Event::Teams { team1, result, margin, team2 } => {
+                let before1: Vec<Rating> = team1.iter().map(|m| self.players[&m.player].rating).collect();
+                let before2: Vec<Rating> = team2.iter().map(|m| self.players[&m.player].rating).collect();
+                let (after1, after2) =
+                    self.algorithm.rate_teams(&before1, &before2, &result.outcome(), &self.settings);
+                let weight = margin.weight(self.settings.margin_weight);
+
+                for (team, before, after) in [(team1, before1, after1), (team2, before2, after2)] {
+                    for (i, member) in team.iter_mut().enumerate() {
+                        let after = before[i].scale_change(after[i], weight);
+                        self.players.get_mut(&member.player).unwrap().rating = after;
+                        member.rating = RatingChange { before: before[i], after };
+                    }
+                }
+            }
+            Event::Ranking { standings } => {
+                let before: Vec<Rating> = standings.iter().map(|s| self.players[&s.player].rating).collect();
+                let places: Vec<usize> = standings.iter().map(|s| s.place).collect();
+                let after = self.algorithm.rate_ranking(&before, &places, &self.settings);
+
+                for (i, standing) in standings.iter_mut().enumerate() {
+                    self.players.get_mut(&standing.player).unwrap().rating = after[i];
+                    standing.rating = RatingChange { before: before[i], after: after[i] };
+                }
+            }
+        }
+    }
+
+    /// Rates both players for a single game worth `weight` games.
+    fn rate_game(&mut self, id1: &str, result: MatchResult, id2: &str, weight: f64) -> (RatingChange, RatingChange) {
+        let MatchResult { result, margin } = result;
+
+        // Handle rating updates with the project's algorithm
+        let player1_old = self.players[id1].rating;
+        let player2_old = self.players[id2].rating;
+
+        let (new_player1, new_player2) = self.algorithm.rate(
+            &player1_old,
+            &player2_old,
+            &result.outcome(),
+            &self.settings,
         );
+        // A decisive margin moves the ratings further than a narrow one.
+        let weight = weight * margin.weight(self.settings.margin_weight);
+        let new_player1 = player1_old.scale_change(new_player1, weight);
+        let new_player2 = player2_old.scale_change(new_player2, weight);
         self.players.get_mut(id1).unwrap().rating = new_player1;
         self.players.get_mut(id2).unwrap().rating = new_player2;
 
-        println!("Game recorded!");
-        let player1_id = id1.to_string();
-        let player2_id = id2.to_string();
-        let new_elo1 = new_player1.rating;
-        let new_elo2 = new_player2.rating;
+        (
+            RatingChange { before: player1_old, after: new_player1 },
+            RatingChange { before: player2_old, after: new_player2 },
+        )
+    }
 
-        println!(
-            "Player {} ELO: {:.2} -> {:.2} (Δ{:.2})",
-            player1_id,
-            player1_old_elo,
-            new_elo1,
-            new_elo1 - player1_old_elo
-        );
-        println!(
-            "Player {} ELO: {:.2} -> {:.2} (Δ{:.2})",
-            player2_id,
-            player2_old_elo,
-            new_elo2,
-            new_elo2 - player2_old_elo
-        );
-        Ok(())
+    /// Rates every player at once over the events recorded in rating period `id`.
+    ///
+    /// Each player's games are rated against the ratings their opponents had when the
+    /// period started, margins aside. Team games and free-for-alls count as a game against
+    /// every opponent. Players without a game in the period go through an empty period,
+    /// which widens their deviation under Glicko and Glicko-2; players who have never
+    /// played keep their initial rating.
+    fn rate_period_records(&mut self, history: &mut [MatchRecord], id: u64) {
+        let mut events: Vec<&mut Event> = history
+            .iter_mut()
+            .filter(|record| record.period == Some(id))
+            .map(|record| &mut record.event)
+            .collect();
+        let before: HashMap<String, Rating> =
+            self.players.iter().map(|(id, player)| (id.clone(), player.rating)).collect();
+
+        let mut results: HashMap<&str, Vec<(Rating, Outcomes)>> = HashMap::new();
+        for event in &events {
+            for (player1, result, player2) in event.pairwise() {
+                results.entry(player1).or_default().push((before[player2], result.outcome()));
+                results.entry(player2).or_default().push((before[player1], result.reversed().outcome()));
+            }
+        }
+        for (id, player) in self.players.iter_mut() {
+            if player.wins + player.losses + player.ties > 0 {
+                let games = results.get(id.as_str()).map_or(&[][..], Vec::as_slice);
+                player.rating = self.algorithm.rate_period(&player.rating, games, &self.settings);
+            }
+        }
+
+        for event in events.iter_mut() {
+            for (id, change) in event.rating_changes_mut() {
+                *change = RatingChange { before: before[id], after: self.players[id].rating };
+            }
+        }
     }
 
     // New helper function to update stats
@@ -150,58 +1210,441 @@ impl EloSystem {
         }
     }
 
+    /// Adds the points scored and conceded in a game recorded with a score.
+    fn add_points(&mut self, id: &str, margin: Margin, is_player1: bool) {
+        if let Margin::Score(score1, score2) = margin {
+            let player = self.players.get_mut(id).unwrap();
+            let (scored, conceded) = if is_player1 { (score1, score2) } else { (score2, score1) };
+            player.points_for += scored;
+            player.points_against += conceded;
+        }
+    }
+
     /// Print the top N players in the system.
-    pub fn print_top(&self, n: Option<usize>) {
+    pub fn print_top(&self, n: Option<usize>, sort: SortBy) {
         let num = n.unwrap_or(5);
+        let leaderboard = self.leaderboard(sort);
+
+        let mut titles = vec!["Player ID", "ELO"];
+        if self.algorithm.has_deviation() {
+            titles.extend(["RD", "95% CI"]);
+        }
+        if self.algorithm.has_volatility() {
+            titles.push("Volatility");
+        }
+        titles.extend(["Wins", "Losses", "Ties"]);
+        let has_points = leaderboard.iter().any(|row| row.points_for + row.points_against > 0);
+        if has_points {
+            titles.extend(["Points for", "Points against"]);
+        }
+
+        let mut table = Table::new();
+        table.set_titles(Row::new(
+            titles.into_iter().map(|title| Cell::new(title).style_spec("Fb")).collect(),
+        ));
+
+        for row in leaderboard.iter().take(num) {
+            let mut cells = vec![
+                Cell::new(&row.id),
+                Cell::new(&format!("{:.1}", row.rating)),
+            ];
+            if let (Some(deviation), Some((lower, upper))) = (row.deviation, row.confidence_interval()) {
+                cells.push(Cell::new(&format!("{:.1}", deviation)));
+                cells.push(Cell::new(&format!("{:.1} – {:.1}", lower, upper)));
+            }
+            if let Some(volatility) = row.volatility {
+                cells.push(Cell::new(&format!("{:.4}", volatility)));
+            }
+            cells.extend([
+                Cell::new(&row.wins.to_string()),
+                Cell::new(&row.losses.to_string()),
+                Cell::new(&row.ties.to_string()),
+            ]);
+            if has_points {
+                cells.push(Cell::new(&row.points_for.to_string()));
+                cells.push(Cell::new(&row.points_against.to_string()));
+            }
+            table.add_row(Row::new(cells));
+        }
+
+        table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        table.printstd();
+    }
+
+    /// Fits every player's strength jointly over the whole match history.
+    ///
+    /// Returns the fit and every player's fitted rating next to their online one, best fitted first.
+    pub fn fit(&self, model: FitModel) -> Result<(Fit, Vec<FittedRating>)> {
+        if self.history.is_empty() {
+            return Err(anyhow!("No matches recorded, nothing to fit."));
+        }
+
+        let ids = self.player_ids();
+        let games = fit::index_games(&ids, &self.history);
+        let fit = match model {
+            FitModel::BradleyTerry => fit::bradley_terry(ids.len(), &games),
+        };
+        let online_ranks: HashMap<String, usize> = self
+            .sorted_players(SortBy::Rating)
+            .into_iter()
+            .enumerate()
+            .map(|(i, player)| (player.id, i + 1))
+            .collect();
+        let mut fitted: Vec<_> = ids.iter().zip(&fit.ratings).collect();
+        fitted.sort_by(|a, b| b.1.total_cmp(a.1));
 
-        // Sort by ELO descending
-        let mut sorted_players: Vec<_> = self.players.values().cloned().collect();
-        sorted_players.sort_by(|a, b| b.rating.rating.partial_cmp(&a.rating.rating).unwrap());
+        let fitted = fitted
+            .into_iter()
+            .enumerate()
+            .map(|(i, (id, &rating))| FittedRating {
+                rank: i + 1,
+                id: id.to_string(),
+                fitted: rating,
+                online_rating: self.players[*id].rating.rating,
+                online_rank: online_ranks[id.as_str()],
+            })
+            .collect();
+        Ok((fit, fitted))
+    }
+
+    /// Print ratings fitted jointly over the whole match history next to the online ratings.
+    pub fn print_fit(&self, model: FitModel, n: Option<usize>) -> Result<()> {
+        let (fit, fitted) = self.fit(model)?;
+
+        println!(
+            "Bradley-Terry fit over {} matches ({} iterations, Davidson tie parameter ν = {:.3})",
+            self.history.len(),
+            fit.iterations,
+            fit.tie_parameter
+        );
 
         let mut table = Table::new();
         table.set_titles(Row::new(vec![
+            Cell::new("Rank").style_spec("Fb"),
             Cell::new("Player ID").style_spec("Fb"),
-            Cell::new("ELO").style_spec("Fb"),
-            Cell::new("Wins").style_spec("Fb"),
-            Cell::new("Losses").style_spec("Fb"),
-            Cell::new("Ties").style_spec("Fb"),
+            Cell::new("Fitted").style_spec("Fb"),
+            Cell::new("Online ELO").style_spec("Fb"),
+            Cell::new("Online rank").style_spec("Fb"),
         ]));
 
-        for player in sorted_players.iter().take(num) {
+        for row in fitted.iter().take(n.unwrap_or(usize::MAX)) {
             table.add_row(Row::new(vec![
-                Cell::new(&player.id),
-                Cell::new(&format!("{:.1}", player.rating.rating)),
-                Cell::new(&player.wins.to_string()),
-                Cell::new(&player.losses.to_string()),
-                Cell::new(&player.ties.to_string()),
+                Cell::new(&row.rank.to_string()),
+                Cell::new(&row.id),
+                Cell::new(&format!("{:.1}", row.fitted)),
+                Cell::new(&format!("{:.1}", row.online_rating)),
+                Cell::new(&row.online_rank.to_string()),
             ]));
         }
 
         table.set_format(*format::consts::FORMAT_BOX_CHARS);
         table.printstd();
+        Ok(())
     }
 
-    /// Print a specific player's stats.
-    pub fn print_info(&self, id: &str) {
-        if let Some(player) = self.players.get(id) {
+    /// Bootstrap intervals of every player's rating and rank, best median first.
+    ///
+    /// The matches in the history are resampled with replacement `samples` times. Each
+    /// resample is rated either by replaying it exactly like `recompute` does, with the
+    /// project's algorithm, settings and rating periods, or, with `model`, by a batch fit.
+    /// Players are ranked by their median rating.
+    pub fn bootstrap(&self, samples: usize, seed: u64, model: Option<FitModel>) -> Result<Vec<BootstrapRating>> {
+        if self.history.is_empty() {
+            return Err(anyhow!("No matches recorded, nothing to resample."));
+        }
+        if samples == 0 {
+            return Err(anyhow!("At least one bootstrap sample is required."));
+        }
+
+        let ids = self.player_ids();
+        let summaries = match model {
+            Some(FitModel::BradleyTerry) => bootstrap::bootstrap(ids.len(), &self.history, samples, seed, |history| {
+                fit::bradley_terry(ids.len(), &fit::index_games(&ids, &history)).ratings
+            }),
+            None => bootstrap::bootstrap(ids.len(), &self.history, samples, seed, |history| {
+                let mut replay = EloSystem {
+                    algorithm: self.algorithm,
+                    players: self.players.clone(),
+                    settings: self.settings.clone(),
+                    history,
+                    saved_matches: None,
+                    periods: self.periods.clone(),
+                };
+                replay.replay_history();
+                ids.iter().map(|id| replay.players[*id].rating.rating).collect()
+            }),
+        };
+        let mut summarized: Vec<_> = ids.iter().zip(summaries).collect();
+        summarized.sort_by(|a, b| b.1.median.total_cmp(&a.1.median));
+        Ok(summarized
+            .into_iter()
+            .enumerate()
+            .map(|(i, (id, summary))| BootstrapRating { rank: i + 1, id: id.to_string(), summary })
+            .collect())
+    }
+
+    /// Print bootstrap intervals of every player's rating and rank.
+    pub fn print_bootstrap(&self, n: Option<usize>, samples: usize, seed: u64, model: Option<FitModel>) -> Result<()> {
+        let summarized = self.bootstrap(samples, seed, model)?;
+
+        let method = match model {
+            Some(FitModel::BradleyTerry) => "Bradley-Terry fit".to_string(),
+            None => format!("{} replay", self.algorithm),
+        };
+        println!(
+            "Bootstrap over {} resamples of {} matches ({}, seed {})",
+            samples,
+            self.history.len(),
+            method,
+            seed
+        );
+
+        let mut table = Table::new();
+        table.set_titles(Row::new(vec![
+            Cell::new("Rank").style_spec("Fb"),
+            Cell::new("Player ID").style_spec("Fb"),
+            Cell::new("Median").style_spec("Fb"),
+            Cell::new("95% CI").style_spec("Fb"),
+            Cell::new("Rank range").style_spec("Fb"),
+        ]));
+
+        for BootstrapRating { rank, id, summary } in summarized.iter().take(n.unwrap_or(5)) {
+            let rank_range = if summary.best_rank == summary.worst_rank {
+                summary.best_rank.to_string()
+            } else {
+                format!("{}–{}", summary.best_rank, summary.worst_rank)
+            };
+            table.add_row(Row::new(vec![
+                Cell::new(&rank.to_string()),
+                Cell::new(id),
+                Cell::new(&format!("{:.1}", summary.median)),
+                Cell::new(&format!("{:.1} – {:.1}", summary.lower, summary.upper)),
+                Cell::new(&rank_range),
+            ]));
+        }
+
+        table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        table.printstd();
+        Ok(())
+    }
+
+    /// Print the last N recorded matches.
+    pub fn print_history(&self, n: usize) {
+        if self.history.is_empty() {
+            println!("No matches recorded.");
+            return;
+        }
+
+        let shown = &self.history[self.history.len().saturating_sub(n)..];
+        let has_periods = shown.iter().any(|record| record.period.is_some());
+        let mut titles = vec!["ID", "Played at"];
+        if has_periods {
+            titles.push("Period");
+        }
+        titles.push("Match");
+
+        let mut table = Table::new();
+        table.set_titles(Row::new(
+            titles.into_iter().map(|title| Cell::new(title).style_spec("Fb")).collect(),
+        ));
+
+        for record in shown {
+            let mut cells = vec![
+                Cell::new(&record.id.to_string()),
+                Cell::new(&record.played_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+            ];
+            if has_periods {
+                cells.push(Cell::new(&record.period.map_or(String::new(), |id| format!("#{}", id))));
+            }
+            let both_bad = if record.both_bad { " (both bad)" } else { "" };
+            cells.push(Cell::new(&format!("{}{}", record.event, both_bad)));
+            table.add_row(Row::new(cells));
+        }
+
+        table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        table.printstd();
+    }
+
+    /// The open rating period, if any, and how it will be closed.
+    pub fn period_status(&self) -> PeriodStatus {
+        let open = self.open_period().cloned();
+        PeriodStatus {
+            matches_waiting: open.as_ref().map_or(0, |period| {
+                self.history.iter().filter(|record| record.period == Some(period.id)).count()
+            }),
+            closes_at: open.as_ref().zip(self.period_length()).map(|(period, length)| period.started_at + length),
+            closed: self.periods.iter().filter(|period| period.closed_at.is_some()).count(),
+            open,
+        }
+    }
+
+    /// Print the open rating period, if any, and how it will be closed.
+    pub fn print_period_status(&self) {
+        let status = self.period_status();
+        let Some(period) = status.open else {
+            println!("No rating period is open; games are rated as they are recorded.");
+            if status.closed > 0 {
+                println!("{} rating periods were closed.", status.closed);
+            }
+            return;
+        };
+
+        println!(
+            "Rating period #{} open since {} with {} matches waiting to be rated ({} closed before it).",
+            period.id,
+            period.started_at.format("%Y-%m-%d %H:%M:%S"),
+            status.matches_waiting,
+            status.closed
+        );
+        if let Some(end) = status.closes_at {
             println!(
-                "Player: {} | ELO: {} | Wins: {} | Losses: {} | Ties: {}",
-                player.id, player.rating.rating.round() as i64, player.wins, player.losses, player.ties
+                "Periods last {} days; the next game recorded after {} starts a new one.",
+                self.settings.rating_period_days,
+                end.format("%Y-%m-%d %H:%M:%S")
             );
         } else {
-            println!("Player with ID '{}' not found.", id);
+            println!("Close it with 'belo period close'.");
         }
     }
 
-    pub fn get_top_n(&self, n: Option<usize>) -> Vec<(String, f64, u32, u32, u32)> {
-        let num = n.unwrap_or(5);
-        let mut sorted_players: Vec<_> = self.players.values().cloned().collect();
-        sorted_players.sort_by(|a, b| b.rating.rating.partial_cmp(&a.rating.rating).unwrap());
-        sorted_players
+    /// Print a specific player's stats.
+    pub fn print_info(&self, id: &str) -> Result<()> {
+        let player = self.players.get(id).ok_or_else(|| BeloError::UnknownPlayer(id.to_string()))?;
+        let player = &Player { rating: self.current_rating(player, Utc::now()), ..player.clone() };
+        let mut uncertainty = String::new();
+        if self.algorithm.has_deviation() {
+            let (lower, upper) = player.rating.confidence_interval();
+            uncertainty.push_str(&format!(
+                " | RD: {} | 95% CI: {} – {}",
+                player.rating.deviation.round() as i64,
+                lower.round() as i64,
+                upper.round() as i64
+            ));
+        }
+        if self.algorithm.has_volatility() {
+            uncertainty.push_str(&format!(" | Volatility: {:.4}", player.rating.volatility));
+        }
+        let mut points = String::new();
+        if player.points_for + player.points_against > 0 {
+            points = format!(" | Points for: {} | Points against: {}", player.points_for, player.points_against);
+        }
+        let last_played = player
+            .last_played
+            .map_or(String::new(), |at| format!(" | Last played: {}", at.format("%Y-%m-%d")));
+        println!(
+            "Player: {} | ELO: {}{} | Wins: {} | Losses: {} | Ties: {}{}{}",
+            player.id,
+            player.rating.rating.round() as i64,
+            uncertainty,
+            player.wins,
+            player.losses,
+            player.ties,
+            points,
+            last_played
+        );
+        Ok(())
+    }
+
+    pub fn get_top_n(&self, n: Option<usize>, sort: SortBy) -> Vec<LeaderboardRow> {
+        let mut leaderboard = self.leaderboard(sort);
+        leaderboard.truncate(n.unwrap_or(5));
+        leaderboard
+    }
+
+    /// A single player's standing on the leaderboard sorted by rating.
+    pub fn standing(&self, id: &str) -> Option<LeaderboardRow> {
+        self.leaderboard(SortBy::Rating).into_iter().find(|row| row.id == id)
+    }
+
+    /// Every player's standing with their rating as of now, best first according to `sort`.
+    pub fn leaderboard(&self, sort: SortBy) -> Vec<LeaderboardRow> {
+        self.sorted_players(sort)
             .into_iter()
-            .take(num)
-            .map(|p| (p.id, p.rating.rating, p.wins, p.losses, p.ties))
+            .enumerate()
+            .map(|(i, player)| LeaderboardRow {
+                rank: i + 1,
+                rating: player.rating.rating,
+                deviation: self.algorithm.has_deviation().then_some(player.rating.deviation),
+                volatility: self.algorithm.has_volatility().then_some(player.rating.volatility),
+                wins: player.wins,
+                losses: player.losses,
+                ties: player.ties,
+                games: player.wins + player.losses + player.ties,
+                points_for: player.points_for,
+                points_against: player.points_against,
+                last_played: player.last_played,
+                id: player.id,
+            })
+            .collect()
+    }
+
+    /// Every player's chance of beating every other, as an N×N matrix flattened row by row.
+    ///
+    /// Players are in the order of `leaderboard(sort)`, and the entry in row `i` and column `j`
+    /// is the chance of the `i`-th player beating the `j`-th. Ties count as neither, so an entry
+    /// and its mirror add up to less than one when the algorithm predicts draws. A player
+    /// can't play themselves, so the diagonal is NaN.
+    pub fn win_probabilities(&self, sort: SortBy) -> Vec<f64> {
+        let ratings: Vec<Rating> = self.sorted_players(sort).iter().map(|player| player.rating).collect();
+        ratings
+            .par_iter()
+            .enumerate()
+            .flat_map_iter(|(i, player1)| {
+                ratings.iter().enumerate().map(move |(j, player2)| {
+                    if i == j {
+                        f64::NAN
+                    } else {
+                        self.algorithm.predict(player1, player2, &self.settings).win
+                    }
+                })
+            })
             .collect()
     }
+
+    /// All player IDs in alphabetical order, so that indexed computations are reproducible.
+    fn player_ids(&self) -> Vec<&String> {
+        let mut ids: Vec<_> = self.players.keys().collect();
+        ids.sort();
+        ids
+    }
+
+    /// All players with their ratings as of now, best first according to `sort`.
+    fn sorted_players(&self, sort: SortBy) -> Vec<Player> {
+        let now = Utc::now();
+        let mut sorted_players: Vec<Player> = self
+            .players
+            .values()
+            .map(|player| Player { rating: self.current_rating(player, now), ..player.clone() })
+            .collect();
+        // Players with equal ratings go by ID, so that the order doesn't depend on the map's.
+        match sort {
+            SortBy::Rating => sorted_players
+                .sort_by(|a, b| b.rating.rating.total_cmp(&a.rating.rating).then_with(|| a.id.cmp(&b.id))),
+            SortBy::Conservative => sorted_players.sort_by(|a, b| {
+                b.rating.conservative().total_cmp(&a.rating.conservative()).then_with(|| a.id.cmp(&b.id))
+            }),
+        }
+        sorted_players
+    }
 }
 
+/// Prints how an event changed its players' ratings, or when it will be rated.
+fn print_rating_changes(record: &MatchRecord) {
+    if let Some(period) = record.period {
+        print_deferred(period);
+        return;
+    }
+    for (id, RatingChange { before, after }) in record.event.rating_changes() {
+        message!(
+            "Player {} ELO: {:.2} -> {:.2} (Δ{:.2})",
+            id,
+            before.rating,
+            after.rating,
+            after.rating - before.rating
+        );
+    }
+}
+
+fn print_deferred(period: u64) {
+    message!("Ratings will be updated when rating period #{} closes.", period);
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// Errors callers may want to tell apart, e.g. to map them to exit codes or exception classes.
+///
+/// They travel inside `anyhow::Error` like every other error and can be recovered with
+/// `downcast_ref`. Anything not listed here, such as I/O errors, is reported as a plain error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BeloError {
+    /// A player ID that is empty or only whitespace.
+    EmptyId,
+    DuplicatePlayer(String),
+    UnknownPlayer(String),
+    /// A game of a player against themselves, or a player merged into themselves.
+    SelfMatch,
+    UnknownMatch(u64),
+    NoActiveProject,
+    UnknownProject(String),
+    DuplicateProject(String),
+    UnknownSetting(String),
+    /// A setting given a value outside its range; `reason` completes "Setting 'key' ...".
+    InvalidSetting { key: String, reason: &'static str },
+}
+
+impl BeloError {
+    /// Exit code of the CLI when a command fails with this error.
+    ///
+    /// 1 is left for other errors and 2 for invalid arguments, which clap reports itself.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            BeloError::NoActiveProject => 3,
+            BeloError::UnknownProject(_) => 4,
+            BeloError::DuplicateProject(_) => 5,
+            BeloError::UnknownPlayer(_) => 6,
+            BeloError::DuplicatePlayer(_) => 7,
+            BeloError::EmptyId => 8,
+            BeloError::SelfMatch => 9,
+            BeloError::UnknownMatch(_) => 10,
+            BeloError::UnknownSetting(_) => 11,
+            BeloError::InvalidSetting { .. } => 12,
+        }
+    }
+
+    /// Stable name of the error, as reported by `--format json`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BeloError::EmptyId => "empty_id",
+            BeloError::DuplicatePlayer(_) => "duplicate_player",
+            BeloError::UnknownPlayer(_) => "unknown_player",
+            BeloError::SelfMatch => "self_match",
+            BeloError::UnknownMatch(_) => "unknown_match",
+            BeloError::NoActiveProject => "no_active_project",
+            BeloError::UnknownProject(_) => "unknown_project",
+            BeloError::DuplicateProject(_) => "duplicate_project",
+            BeloError::UnknownSetting(_) => "unknown_setting",
+            BeloError::InvalidSetting { .. } => "invalid_setting",
+        }
+    }
+}
+
+impl fmt::Display for BeloError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BeloError::EmptyId => write!(f, "Player ID cannot be empty."),
+            BeloError::DuplicatePlayer(id) => write!(f, "Player with ID '{}' already exists.", id),
+            BeloError::UnknownPlayer(id) => write!(f, "Player with ID '{}' not found.", id),
+            BeloError::SelfMatch => write!(f, "A player cannot play against or be merged into themselves."),
+            BeloError::UnknownMatch(id) => write!(f, "Match #{} not found.", id),
+            BeloError::NoActiveProject => write!(f, "No active project. Please activate a project first"),
+            BeloError::UnknownProject(name) => write!(f, "Project '{}' does not exist.", name),
+            BeloError::DuplicateProject(name) => write!(f, "Project '{}' already exists.", name),
+            BeloError::UnknownSetting(key) => write!(
+                f,
+                "Unknown setting '{}'. Valid settings are: {}",
+                key,
+                crate::settings::SETTING_KEYS.join(", ")
+            ),
+            BeloError::InvalidSetting { key, reason } => write!(f, "Setting '{}' {}.", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for BeloError {}
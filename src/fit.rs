@@ -0,0 +1,123 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::elo_system::GameResult;
+use crate::history::MatchRecord;
+
+/// Offline models that fit every player's strength jointly from the whole match history.
+#[derive(Clone, Copy, ValueEnum, Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FitModel {
+    /// Bradley-Terry, with ties handled by Davidson's extension
+    #[default]
+    BradleyTerry,
+}
+
+/// Strengths fitted by maximum likelihood, on the Elo scale.
+#[derive(Debug, Clone)]
+pub struct Fit {
+    pub ratings: Vec<f64>,
+    /// Davidson's tie parameter ν; zero when no game was tied.
+    pub tie_parameter: f64,
+    pub iterations: usize,
+}
+
+/// A game between two players, referred to by their index in a player list.
+pub type IndexedGame = (usize, usize, GameResult);
+
+const MAX_ITERATIONS: usize = 10_000;
+const TOLERANCE: f64 = 1e-9;
+
+/// Converts the history into games between player indices, using the order of `player_ids`.
+///
+/// A free-for-all counts as one game between every pair of its players. Games involving players that aren't listed are skipped.
+pub fn index_games(player_ids: &[&String], history: &[MatchRecord]) -> Vec<IndexedGame> {
+    let index: HashMap<&str, usize> = player_ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id.as_str(), i))
+        .collect();
+    history
+        .iter()
+        .flat_map(|r| r.event.pairwise())
+        .filter_map(|(id1, result, id2)| Some((*index.get(id1.as_str())?, *index.get(id2.as_str())?, result)))
+        .collect()
+}
+
+/// Fits a Bradley-Terry model with Davidson ties to the given games using
+/// Hunter's (2004) minorization-maximization algorithm.
+///
+/// Unlike the online ratings, the result doesn't depend on the order of the games.
+/// Every player also gets one virtual win and one virtual loss against a reference
+/// player of average strength, which keeps the strengths of undefeated or winless
+/// players finite and anchors the scale at 1500.
+pub fn bradley_terry(num_players: usize, games: &[IndexedGame]) -> Fit {
+    // Only the number of wins and ties per pair matters, which keeps
+    // each iteration cheap even for very long histories.
+    let mut pairs: BTreeMap<(usize, usize), (f64, f64, f64)> = BTreeMap::new();
+    for &(i, j, result) in games {
+        let (key, result) = if i < j { ((i, j), result) } else { ((j, i), result.reversed()) };
+        let counts = pairs.entry(key).or_default();
+        match result {
+            GameResult::Player1Wins => counts.0 += 1.0,
+            GameResult::Player2Wins => counts.1 += 1.0,
+            GameResult::Tie => counts.2 += 1.0,
+        }
+    }
+    let pairs: Vec<(usize, usize, f64, f64, f64)> = pairs
+        .into_iter()
+        .map(|((i, j), (wins_i, wins_j, ties))| (i, j, wins_i, wins_j, ties))
+        .collect();
+
+    // Points scored by each player (a tie is half a point), plus the virtual win.
+    let mut points = vec![1.0; num_players];
+    let mut ties = 0.0;
+    for &(i, j, wins_i, wins_j, pair_ties) in &pairs {
+        points[i] += wins_i + 0.5 * pair_ties;
+        points[j] += wins_j + 0.5 * pair_ties;
+        ties += pair_ties;
+    }
+
+    let mut strengths = vec![1.0; num_players];
+    let mut nu = if ties > 0.0 { 1.0 } else { 0.0 };
+    let mut iterations = 0;
+    while iterations < MAX_ITERATIONS {
+        iterations += 1;
+
+        // Virtual games against the reference player of strength 1.
+        let mut denominators: Vec<f64> = strengths.iter().map(|s| 2.0 / (s + 1.0)).collect();
+        for &(i, j, wins_i, wins_j, pair_ties) in &pairs {
+            let n = wins_i + wins_j + pair_ties;
+            let (si, sj) = (strengths[i], strengths[j]);
+            let d = si + sj + nu * (si * sj).sqrt();
+            denominators[i] += n * (1.0 + 0.5 * nu * (sj / si).sqrt()) / d;
+            denominators[j] += n * (1.0 + 0.5 * nu * (si / sj).sqrt()) / d;
+        }
+        let updated: Vec<f64> = points.iter().zip(&denominators).map(|(p, d)| p / d).collect();
+
+        if ties > 0.0 {
+            let expected: f64 = pairs
+                .iter()
+                .map(|&(i, j, wins_i, wins_j, pair_ties)| {
+                    let (si, sj) = (updated[i], updated[j]);
+                    (wins_i + wins_j + pair_ties) * (si * sj).sqrt() / (si + sj + nu * (si * sj).sqrt())
+                })
+                .sum();
+            nu = ties / expected;
+        }
+
+        let change = strengths
+            .iter()
+            .zip(&updated)
+            .map(|(old, new)| (new.ln() - old.ln()).abs())
+            .fold(0.0, f64::max);
+        strengths = updated;
+        if change < TOLERANCE {
+            break;
+        }
+    }
+
+    let ratings = strengths.iter().map(|s| 1500.0 + 400.0 * s.log10()).collect();
+    Fit { ratings, tie_parameter: nu, iterations }
+}
@@ -0,0 +1,430 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use crate::elo_system::{GameResult, Margin, MatchResult};
+use crate::project::write_atomic;
+use crate::rating::Rating;
+
+/// A player's rating right before and right after a game.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct RatingChange {
+    pub before: Rating,
+    pub after: Rating,
+}
+
+/// A single event, as stored in the project's match history.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatchRecord {
+    pub id: u64,
+    pub played_at: DateTime<Utc>,
+    /// The rating period the event was rated in, if it was recorded while one was open.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<u64>,
+    /// The conversation or question an imported game was judged on, used to skip duplicates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<String>,
+    /// A tie where both sides were judged bad, as in Chatbot Arena's `tie (bothbad)`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub both_bad: bool,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// A match as reported by `--format json`.
+///
+/// Unlike the stored layout, which leaves out fields holding their default, every field
+/// is always present, with `null` where there is no value, and `type` tells the kind of event.
+#[derive(Serialize)]
+pub struct MatchOutput<'a> {
+    id: u64,
+    played_at: DateTime<Utc>,
+    period: Option<u64>,
+    source_id: Option<&'a str>,
+    both_bad: bool,
+    #[serde(flatten)]
+    event: EventOutput<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventOutput<'a> {
+    Duel {
+        player1: &'a str,
+        result: GameResult,
+        margin: Margin,
+        player2: &'a str,
+        weight: f64,
+        player1_rating: &'a RatingChange,
+        player2_rating: &'a RatingChange,
+    },
+    Series {
+        player1: &'a str,
+        player2: &'a str,
+        games: Vec<ResultOutput>,
+        player1_rating: &'a RatingChange,
+        player2_rating: &'a RatingChange,
+    },
+    Teams {
+        team1: &'a [TeamMember],
+        result: GameResult,
+        margin: Margin,
+        team2: &'a [TeamMember],
+    },
+    Ranking {
+        standings: &'a [Standing],
+    },
+}
+
+/// A game of a series, with its margin even when it's normal.
+#[derive(Serialize)]
+struct ResultOutput {
+    result: GameResult,
+    margin: Margin,
+}
+
+impl MatchRecord {
+    /// The record in the layout reported by `--format json`.
+    pub fn output(&self) -> MatchOutput<'_> {
+        let event = match &self.event {
+            Event::Duel { player1, result, margin, player2, weight, player1_rating, player2_rating } => {
+                EventOutput::Duel {
+                    player1,
+                    result: *result,
+                    margin: *margin,
+                    player2,
+                    weight: *weight,
+                    player1_rating,
+                    player2_rating,
+                }
+            }
+            Event::Series { player1, player2, games, player1_rating, player2_rating } => EventOutput::Series {
+                player1,
+                player2,
+                games: games.iter().map(|game| ResultOutput { result: game.result, margin: game.margin }).collect(),
+                player1_rating,
+                player2_rating,
+            },
+            Event::Teams { team1, result, margin, team2 } => {
+                EventOutput::Teams { team1, result: *result, margin: *margin, team2 }
+            }
+            Event::Ranking { standings } => EventOutput::Ranking { standings },
+        };
+        MatchOutput {
+            id: self.id,
+            played_at: self.played_at,
+            period: self.period,
+            source_id: self.source_id.as_deref(),
+            both_bad: self.both_bad,
+            event,
+        }
+    }
+}
+
+/// A rating period. Events recorded while it is open are rated together when it closes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RatingPeriod {
+    pub id: u64,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the period is still open.
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// What happened in a recorded event.
+///
+/// Two-player games keep the flat layout they have always been stored with,
+/// so histories written by earlier versions still load.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Event {
+    /// A game between two players.
+    Duel {
+        player1: String,
+        result: GameResult,
+        #[serde(default, skip_serializing_if = "Margin::is_normal")]
+        margin: Margin,
+        player2: String,
+        /// How many games' worth of rating change the game is worth, e.g. from an import.
+        #[serde(default = "default_weight", skip_serializing_if = "is_default_weight")]
+        weight: f64,
+        player1_rating: RatingChange,
+        player2_rating: RatingChange,
+    },
+    /// A series between two players, rated as a single rating period.
+    Series {
+        player1: String,
+        player2: String,
+        /// Results from player 1's point of view, in the order they were played.
+        games: Vec<MatchResult>,
+        player1_rating: RatingChange,
+        player2_rating: RatingChange,
+    },
+    /// A game between two teams, with one result for the whole team.
+    Teams {
+        team1: Vec<TeamMember>,
+        result: GameResult,
+        #[serde(default, skip_serializing_if = "Margin::is_normal")]
+        margin: Margin,
+        team2: Vec<TeamMember>,
+    },
+    /// A free-for-all with a full finishing order, best first.
+    Ranking { standings: Vec<Standing> },
+}
+
+/// A player who took part in a team game.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TeamMember {
+    pub player: String,
+    pub rating: RatingChange,
+}
+
+/// A player's finishing place in a free-for-all. Tied players share a place.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Standing {
+    pub player: String,
+    /// Finishing place, starting at 1.
+    pub place: usize,
+    pub rating: RatingChange,
+}
+
+impl Event {
+    /// Every player taking part in the event.
+    pub fn players(&self) -> Vec<&String> {
+        match self {
+            Event::Duel { player1, player2, .. } | Event::Series { player1, player2, .. } => vec![player1, player2],
+            Event::Teams { team1, team2, .. } => team1.iter().chain(team2).map(|m| &m.player).collect(),
+            Event::Ranking { standings } => standings.iter().map(|s| &s.player).collect(),
+        }
+    }
+
+    /// Mutable access to every player ID, e.g. to rename a player.
+    pub fn players_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Event::Duel { player1, player2, .. } | Event::Series { player1, player2, .. } => vec![player1, player2],
+            Event::Teams { team1, team2, .. } => {
+                team1.iter_mut().chain(team2.iter_mut()).map(|m| &mut m.player).collect()
+            }
+            Event::Ranking { standings } => standings.iter_mut().map(|s| &mut s.player).collect(),
+        }
+    }
+
+    /// Every player's rating change in the event.
+    pub fn rating_changes(&self) -> Vec<(&String, &RatingChange)> {
+        match self {
+            Event::Duel { player1, player2, player1_rating, player2_rating, .. }
+            | Event::Series { player1, player2, player1_rating, player2_rating, .. } => {
+                vec![(player1, player1_rating), (player2, player2_rating)]
+            }
+            Event::Teams { team1, team2, .. } => team1.iter().chain(team2).map(|m| (&m.player, &m.rating)).collect(),
+            Event::Ranking { standings } => standings.iter().map(|s| (&s.player, &s.rating)).collect(),
+        }
+    }
+
+    /// Mutable access to every player's rating change, e.g. to store ratings computed later.
+    pub fn rating_changes_mut(&mut self) -> Vec<(&String, &mut RatingChange)> {
+        match self {
+            Event::Duel { player1, player2, player1_rating, player2_rating, .. }
+            | Event::Series { player1, player2, player1_rating, player2_rating, .. } => {
+                vec![(&*player1, player1_rating), (&*player2, player2_rating)]
+            }
+            Event::Teams { team1, team2, .. } => {
+                team1.iter_mut().chain(team2.iter_mut()).map(|m| (&m.player, &mut m.rating)).collect()
+            }
+            Event::Ranking { standings } => standings.iter_mut().map(|s| (&s.player, &mut s.rating)).collect(),
+        }
+    }
+
+    /// The result and its margin, for events that have a single result.
+    pub fn result_mut(&mut self) -> Option<(&mut GameResult, &mut Margin)> {
+        match self {
+            Event::Duel { result, margin, .. } | Event::Teams { result, margin, .. } => Some((result, margin)),
+            Event::Series { .. } | Event::Ranking { .. } => None,
+        }
+    }
+
+    /// The total number of wins, losses and ties the event adds to its players' stats.
+    pub fn results_recorded(&self) -> usize {
+        match self {
+            Event::Duel { .. } => 2,
+            Event::Series { games, .. } => 2 * games.len(),
+            Event::Teams { team1, team2, .. } => team1.len() + team2.len(),
+            Event::Ranking { standings } => standings.len() * (standings.len() - 1),
+        }
+    }
+
+    /// Takes a player out of the event, returning whether enough players are left to rate it.
+    ///
+    /// A two-player game or series can't go on without one of its players, a team game needs
+    /// a member left on each side and a free-for-all needs two players.
+    pub fn remove_player(&mut self, id: &str) -> bool {
+        match self {
+            Event::Duel { player1, player2, .. } | Event::Series { player1, player2, .. } => {
+                player1 != id && player2 != id
+            }
+            Event::Teams { team1, team2, .. } => {
+                team1.retain(|member| member.player != id);
+                team2.retain(|member| member.player != id);
+                !team1.is_empty() && !team2.is_empty()
+            }
+            Event::Ranking { standings } => {
+                standings.retain(|standing| standing.player != id);
+                standings.len() >= 2
+            }
+        }
+    }
+
+    /// The event broken down into one game per pair of opponents.
+    pub fn pairwise(&self) -> Vec<(&String, GameResult, &String)> {
+        match self {
+            Event::Duel { player1, result, player2, .. } => vec![(player1, *result, player2)],
+            Event::Series { player1, player2, games, .. } => {
+                games.iter().map(|game| (player1, game.result, player2)).collect()
+            }
+            Event::Teams { team1, result, team2, .. } => team1
+                .iter()
+                .flat_map(|a| team2.iter().map(move |b| (&a.player, *result, &b.player)))
+                .collect(),
+            Event::Ranking { standings } => {
+                let mut games = Vec::new();
+                for (i, a) in standings.iter().enumerate() {
+                    for b in &standings[i + 1..] {
+                        games.push((&a.player, place_result(a.place, b.place), &b.player));
+                    }
+                }
+                games
+            }
+        }
+    }
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+fn is_default_weight(weight: &f64) -> bool {
+    *weight == 1.0
+}
+
+/// The result of a game between players finishing at `place1` and `place2`.
+pub fn place_result(place1: usize, place2: usize) -> GameResult {
+    match place1.cmp(&place2) {
+        std::cmp::Ordering::Less => GameResult::Player1Wins,
+        std::cmp::Ordering::Greater => GameResult::Player2Wins,
+        std::cmp::Ordering::Equal => GameResult::Tie,
+    }
+}
+
+/// Parses a finishing order such as `a > b = c > d` into groups of tied players, best first.
+pub fn parse_ranking(ranking: &str) -> Result<Vec<Vec<String>>> {
+    ranking
+        .split('>')
+        .map(|group| {
+            group
+                .split('=')
+                .map(|id| {
+                    let id = id.trim();
+                    if id.is_empty() {
+                        Err(anyhow!("Invalid ranking '{}', expected e.g. 'a > b = c > d'.", ranking.trim()))
+                    } else {
+                        Ok(id.to_string())
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Duel { player1, result, margin, player2, weight, .. } => {
+                let result = MatchResult { result: *result, margin: *margin };
+                write!(f, "{} {} {}", player1, result, player2)?;
+                if !is_default_weight(weight) {
+                    write!(f, " (×{})", weight)?;
+                }
+                Ok(())
+            }
+            Event::Series { player1, player2, games, .. } => {
+                let games: Vec<String> = games.iter().map(ToString::to_string).collect();
+                write!(f, "{} [{}] {}", player1, games.join(","), player2)
+            }
+            Event::Teams { team1, result, margin, team2 } => {
+                let names = |team: &[TeamMember]| team.iter().map(|m| m.player.as_str()).collect::<Vec<_>>().join(",");
+                let result = MatchResult { result: *result, margin: *margin };
+                write!(f, "{} {} {}", names(team1), result, names(team2))
+            }
+            Event::Ranking { standings } => {
+                for (i, standing) in standings.iter().enumerate() {
+                    if i > 0 {
+                        let separator = if standing.place == standings[i - 1].place { "=" } else { ">" };
+                        write!(f, " {} ", separator)?;
+                    }
+                    write!(f, "{}", standing.player)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for MatchRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}", self.id, self.event)
+    }
+}
+
+/// Reads a match history stored as JSON Lines, one `MatchRecord` per line.
+///
+/// Projects created before the history was recorded have no file and an empty history.
+pub fn load_history(path: &Path) -> Result<Vec<MatchRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to open match history at '{:?}'", path))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Failed to parse match history line {}", i + 1))
+        })
+        .collect()
+}
+
+/// Writes the match history as JSON Lines, replacing the existing file.
+pub fn save_history(path: &Path, history: &[MatchRecord]) -> Result<()> {
+    write_atomic(path, to_json_lines(history)?.as_bytes())
+        .with_context(|| format!("Failed to create match history at '{:?}'", path))
+}
+
+/// Adds records to the end of a match history stored as JSON Lines, creating it if needed.
+pub fn append_history(path: &Path, records: &[MatchRecord]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open match history at '{:?}'", path))?;
+
+    file.write_all(to_json_lines(records)?.as_bytes())?;
+    Ok(())
+}
+
+fn to_json_lines(records: &[MatchRecord]) -> Result<String> {
+    let mut serialized = String::new();
+    for record in records {
+        serialized.push_str(&serde_json::to_string(record)?);
+        serialized.push('\n');
+    }
+    Ok(serialized)
+}
@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use clap::{Args, ValueEnum};
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::elo_system::{GameResult, MatchResult};
+
+/// File formats `belo import` can read.
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// JSON Lines, one object per game
+    Jsonl,
+    /// Chatbot Arena battle logs: JSON Lines with `model_a`, `model_b` and a `winner` of
+    /// `model_a`, `model_b`, `tie` or `tie (bothbad)`
+    Arena,
+}
+
+impl ImportFormat {
+    /// Guesses the format from the file extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("csv") => Ok(ImportFormat::Csv),
+            Some("jsonl") | Some("ndjson") => Ok(ImportFormat::Jsonl),
+            _ => Err(anyhow!("Cannot tell the format of {:?} from its extension; pass --from.", path)),
+        }
+    }
+}
+
+/// Which columns (or JSON keys) hold each field of an imported game.
+///
+/// Columns that aren't given default to the names the format uses.
+#[derive(Args, Debug, Clone)]
+pub struct ColumnMap {
+    /// Column with the first player's ID [default: player1, or model_a for arena]
+    #[arg(long)]
+    pub player1_column: Option<String>,
+    /// Column with the second player's ID [default: player2, or model_b for arena]
+    #[arg(long)]
+    pub player2_column: Option<String>,
+    /// Column with the result from the first player's point of view; accepts everything `vs`
+    /// does, or the winner for arena [default: result, or winner for arena]
+    #[arg(long)]
+    pub result_column: Option<String>,
+    /// Optional column with the time the game was played: RFC 3339, `YYYY-MM-DD HH:MM:SS`
+    /// or `YYYY-MM-DD` in UTC, or Unix seconds. Games without one get the time of the import
+    /// [default: timestamp, or tstamp for arena]
+    #[arg(long)]
+    pub timestamp_column: Option<String>,
+    /// Column with how many games' worth of rating change a game is worth. Without it, or
+    /// where it's empty, every game counts once
+    #[arg(long)]
+    pub weight_column: Option<String>,
+    /// Skip games whose value in this column, e.g. question_id, was already imported
+    /// from this file or an earlier one
+    #[arg(long, value_name = "COLUMN")]
+    pub dedupe_by: Option<String>,
+}
+
+impl ColumnMap {
+    /// The column names to read, with the format's defaults filled in.
+    fn resolve(&self, format: ImportFormat) -> Columns<'_> {
+        let (player1, player2, result, timestamp) = match format {
+            ImportFormat::Csv | ImportFormat::Jsonl => ("player1", "player2", "result", "timestamp"),
+            ImportFormat::Arena => ("model_a", "model_b", "winner", "tstamp"),
+        };
+        Columns {
+            player1: self.player1_column.as_deref().unwrap_or(player1),
+            player2: self.player2_column.as_deref().unwrap_or(player2),
+            result: self.result_column.as_deref().unwrap_or(result),
+            timestamp: self.timestamp_column.as_deref().unwrap_or(timestamp),
+            weight: self.weight_column.as_deref(),
+            source_id: self.dedupe_by.as_deref(),
+        }
+    }
+}
+
+struct Columns<'a> {
+    player1: &'a str,
+    player2: &'a str,
+    result: &'a str,
+    timestamp: &'a str,
+    weight: Option<&'a str>,
+    source_id: Option<&'a str>,
+}
+
+/// A game read from an import file.
+#[derive(Debug, Clone)]
+pub struct ImportedGame {
+    /// Line of the file the game was read from, for error messages.
+    pub line: usize,
+    pub player1: String,
+    pub result: MatchResult,
+    pub player2: String,
+    pub played_at: Option<DateTime<Utc>>,
+    pub weight: f64,
+    /// The value of the `--dedupe-by` column, if one was given.
+    pub source_id: Option<String>,
+    /// A tie where both sides were judged bad, from an arena `tie (bothbad)`.
+    pub both_bad: bool,
+}
+
+/// A row of an import file, by column name. Empty cells are left out.
+type Row = HashMap<String, String>;
+
+/// How many invalid rows an error lists before summing up the rest.
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// Reads every game in the file at `path`, in file order.
+///
+/// Fails without returning any game if a single row is invalid, listing the problems found.
+pub fn read_games(path: &Path, format: ImportFormat, columns: &ColumnMap) -> Result<Vec<ImportedGame>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to open import file at '{:?}'", path))?;
+    let rows = match format {
+        ImportFormat::Csv => csv_rows(&contents)?,
+        ImportFormat::Jsonl | ImportFormat::Arena => jsonl_rows(&contents),
+    };
+    let columns = columns.resolve(format);
+
+    let mut games = Vec::with_capacity(rows.len());
+    let mut errors = Vec::new();
+    for (line, row) in rows {
+        match row.and_then(|row| parse_game(line, &row, &columns, format)) {
+            Ok(game) => games.push(game),
+            Err(e) => errors.push(format!("line {}: {}", line, e)),
+        }
+    }
+    reject_invalid(errors)?;
+    Ok(games)
+}
+
+/// Fails with the problems found in an import, if any, so that nothing gets imported.
+pub fn reject_invalid(errors: Vec<String>) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!("Nothing was imported, {} rows are invalid:", errors.len());
+    for error in errors.iter().take(MAX_REPORTED_ERRORS) {
+        message.push_str("\n  ");
+        message.push_str(error);
+    }
+    if errors.len() > MAX_REPORTED_ERRORS {
+        message.push_str(&format!("\n  ... and {} more", errors.len() - MAX_REPORTED_ERRORS));
+    }
+    Err(anyhow!(message))
+}
+
+fn csv_rows(contents: &str) -> Result<Vec<(usize, std::result::Result<Row, String>)>> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(contents.as_bytes());
+    let headers = reader.headers().with_context(|| "Failed to read the CSV header row")?.clone();
+    Ok(reader
+        .records()
+        .map(|record| match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |position| position.line() as usize);
+                let row = headers
+                    .iter()
+                    .zip(record.iter())
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(column, value)| (column.to_string(), value.to_string()))
+                    .collect();
+                (line, Ok(row))
+            }
+            Err(e) => {
+                let line = e.position().map_or(0, |position| position.line() as usize);
+                (line, Err(e.to_string()))
+            }
+        })
+        .collect())
+}
+
+fn jsonl_rows(contents: &str) -> Vec<(usize, std::result::Result<Row, String>)> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let row = match serde_json::from_str::<serde_json::Value>(line) {
+                Ok(serde_json::Value::Object(object)) => Ok(object
+                    .into_iter()
+                    .filter_map(|(key, value)| {
+                        let value = match value {
+                            serde_json::Value::Null => return None,
+                            serde_json::Value::String(value) => value.trim().to_string(),
+                            value => value.to_string(),
+                        };
+                        (!value.is_empty()).then_some((key, value))
+                    })
+                    .collect()),
+                Ok(_) => Err("Expected a JSON object.".to_string()),
+                Err(e) => Err(format!("Invalid JSON: {}", e)),
+            };
+            (i + 1, row)
+        })
+        .collect()
+}
+
+fn parse_game(
+    line: usize,
+    row: &Row,
+    columns: &Columns,
+    format: ImportFormat,
+) -> std::result::Result<ImportedGame, String> {
+    let required = |column: &str| row.get(column).cloned().ok_or_else(|| format!("Missing '{}'.", column));
+    let player1 = required(columns.player1)?;
+    let player2 = required(columns.player2)?;
+    let result = required(columns.result)?;
+    let (result, both_bad) = match format {
+        ImportFormat::Arena => parse_arena_winner(&result)?,
+        ImportFormat::Csv | ImportFormat::Jsonl => (result.parse()?, false),
+    };
+    let source_id = match columns.source_id {
+        Some(column) => Some(required(column)?),
+        None => None,
+    };
+    let played_at = row.get(columns.timestamp).map(|value| parse_timestamp(value)).transpose()?;
+    let weight = match columns.weight.and_then(|column| row.get(column)) {
+        Some(value) => match value.parse::<f64>() {
+            Ok(weight) if weight.is_finite() && weight >= 0.0 => weight,
+            _ => return Err(format!("Invalid weight '{}', expected a non-negative number.", value)),
+        },
+        None => 1.0,
+    };
+    Ok(ImportedGame { line, player1, result, player2, played_at, weight, source_id, both_bad })
+}
+
+/// Maps an arena `winner` onto a result, telling whether it was a tie with both sides bad.
+fn parse_arena_winner(winner: &str) -> std::result::Result<(MatchResult, bool), String> {
+    let (result, both_bad) = match winner {
+        "model_a" => (GameResult::Player1Wins, false),
+        "model_b" => (GameResult::Player2Wins, false),
+        "tie" => (GameResult::Tie, false),
+        "tie (bothbad)" => (GameResult::Tie, true),
+        _ => {
+            return Err(format!(
+                "Invalid winner '{}'. Use model_a, model_b, tie or tie (bothbad).",
+                winner
+            ))
+        }
+    };
+    Ok((result.into(), both_bad))
+}
+
+fn parse_timestamp(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(at) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(at.and_utc());
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    // Arena logs store fractional Unix seconds.
+    if let Some(at) = value.parse::<f64>().ok().and_then(|seconds| {
+        let micros = (seconds.fract() * 1e6).round() as u32;
+        DateTime::from_timestamp(seconds.floor() as i64, micros * 1000)
+    }) {
+        return Ok(at);
+    }
+    Err(format!(
+        "Invalid timestamp '{}', expected e.g. 2025-01-31T12:00:00Z, 2025-01-31 12:00:00, 2025-01-31 or Unix seconds.",
+        value
+    ))
+}
@@ -0,0 +1,182 @@
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+/// A player's standing on the leaderboard, with their rating as of now.
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardRow {
+    /// Position on the leaderboard, starting at 1.
+    pub rank: usize,
+    pub id: String,
+    pub rating: f64,
+    /// `None` when the project's algorithm doesn't track a deviation.
+    pub deviation: Option<f64>,
+    /// `None` when the project's algorithm doesn't track a volatility.
+    pub volatility: Option<f64>,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    pub games: u32,
+    pub points_for: u32,
+    pub points_against: u32,
+    pub last_played: Option<DateTime<Utc>>,
+}
+
+impl LeaderboardRow {
+    /// The 95% confidence interval, rating ± 1.96·RD, if the rating has a deviation.
+    pub fn confidence_interval(&self) -> Option<(f64, f64)> {
+        self.deviation.map(|rd| (self.rating - 1.96 * rd, self.rating + 1.96 * rd))
+    }
+}
+
+/// Formats `belo export` can write the leaderboard in.
+#[derive(Clone, Copy, ValueEnum, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Markdown,
+    Latex,
+}
+
+/// Renders the leaderboard in the given format.
+///
+/// CSV and JSON keep full precision for other tools; Markdown and LaTeX round like `belo head`.
+/// Every format has the same columns whatever the algorithm, with empty cells (or `null`)
+/// for a deviation, volatility or last game the player doesn't have.
+pub fn render(rows: &[LeaderboardRow], format: ExportFormat) -> Result<String> {
+    let columns = columns();
+    match format {
+        ExportFormat::Json => {
+            let rows: Vec<JsonRow> = rows.iter().map(|row| JsonRow { row, columns: &columns }).collect();
+            Ok(serde_json::to_string_pretty(&rows)? + "\n")
+        }
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(columns.iter().map(|column| column.key))?;
+            for row in rows {
+                writer.write_record(columns.iter().map(|column| (column.value)(row).raw()))?;
+            }
+            Ok(String::from_utf8(writer.into_inner()?)?)
+        }
+        ExportFormat::Markdown => {
+            let titles: Vec<&str> = columns.iter().map(|column| column.title).collect();
+            let alignment: Vec<&str> =
+                columns.iter().map(|column| if column.numeric { "---:" } else { ":---" }).collect();
+            let mut out = format!("| {} |\n| {} |\n", titles.join(" | "), alignment.join(" | "));
+            for row in rows {
+                let cells: Vec<String> =
+                    columns.iter().map(|column| (column.value)(row).rounded().replace('|', "\\|")).collect();
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+            Ok(out)
+        }
+        ExportFormat::Latex => {
+            let spec: String = columns.iter().map(|column| if column.numeric { 'r' } else { 'l' }).collect();
+            let titles: Vec<&str> = columns.iter().map(|column| column.title).collect();
+            let mut out = format!("\\begin{{tabular}}{{{}}}\n\\hline\n{} \\\\\n\\hline\n", spec, titles.join(" & "));
+            for row in rows {
+                let cells: Vec<String> =
+                    columns.iter().map(|column| escape_latex(&(column.value)(row).rounded())).collect();
+                out.push_str(&format!("{} \\\\\n", cells.join(" & ")));
+            }
+            out.push_str("\\hline\n\\end{tabular}\n");
+            Ok(out)
+        }
+    }
+}
+
+/// A leaderboard column, as written by `render`.
+struct Column {
+    /// Header used by CSV, matching the JSON field name.
+    key: &'static str,
+    /// Header used by Markdown and LaTeX.
+    title: &'static str,
+    numeric: bool,
+    value: fn(&LeaderboardRow) -> Value,
+}
+
+/// A row written as a JSON object with one field per column, in column order.
+struct JsonRow<'a> {
+    row: &'a LeaderboardRow,
+    columns: &'a [Column],
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for column in self.columns {
+            match (column.value)(self.row) {
+                Value::Text(text) => map.serialize_entry(column.key, &text)?,
+                Value::Count(count) => map.serialize_entry(column.key, &count)?,
+                Value::Number(value, _) => map.serialize_entry(column.key, &value)?,
+                Value::Time(at) => map.serialize_entry(column.key, &at)?,
+            }
+        }
+        map.end()
+    }
+}
+
+enum Value {
+    Text(String),
+    Count(u64),
+    /// A measurement and the number of decimals it is shown with when rounded.
+    Number(Option<f64>, usize),
+    /// A point in time, shown as a date when rounded.
+    Time(Option<DateTime<Utc>>),
+}
+
+impl Value {
+    fn raw(&self) -> String {
+        match self {
+            Value::Number(Some(value), _) => value.to_string(),
+            Value::Time(Some(at)) => at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            _ => self.rounded(),
+        }
+    }
+
+    fn rounded(&self) -> String {
+        match self {
+            Value::Text(text) => text.clone(),
+            Value::Count(count) => count.to_string(),
+            Value::Number(Some(value), decimals) => format!("{:.*}", decimals, value),
+            Value::Time(Some(at)) => at.format("%Y-%m-%d").to_string(),
+            Value::Number(None, _) | Value::Time(None) => String::new(),
+        }
+    }
+}
+
+fn columns() -> [Column; 12] {
+    let column = |key, title, value| Column { key, title, numeric: true, value };
+    [
+        column("rank", "Rank", |r| Value::Count(r.rank as u64)),
+        Column { key: "id", title: "Player", numeric: false, value: |r| Value::Text(r.id.clone()) },
+        column("rating", "Rating", |r| Value::Number(Some(r.rating), 1)),
+        column("deviation", "RD", |r| Value::Number(r.deviation, 1)),
+        column("volatility", "Volatility", |r| Value::Number(r.volatility, 4)),
+        column("wins", "Wins", |r| Value::Count(r.wins.into())),
+        column("losses", "Losses", |r| Value::Count(r.losses.into())),
+        column("ties", "Ties", |r| Value::Count(r.ties.into())),
+        column("games", "Games", |r| Value::Count(r.games.into())),
+        column("points_for", "Points for", |r| Value::Count(r.points_for.into())),
+        column("points_against", "Points against", |r| Value::Count(r.points_against.into())),
+        Column { key: "last_played", title: "Last played", numeric: false, value: |r| Value::Time(r.last_played) },
+    ]
+}
+
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
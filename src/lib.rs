@@ -1,80 +1,571 @@
-mod elo_system;
+pub mod bootstrap;
+pub mod config;
+pub mod elo_system;
+pub mod error;
+pub mod fit;
+pub mod history;
+pub mod import;
+pub mod leaderboard;
+pub mod output;
+pub mod project;
+pub mod rating;
+pub mod settings;
 
+use clap::ValueEnum;
 use pyo3::prelude::*;
-use crate::elo_system::{EloSystem, GameResult};
+use crate::elo_system::{EloSystem, GameResult, MatchResult, SortBy};
+use crate::history::parse_ranking;
+use crate::leaderboard::LeaderboardRow;
+use crate::rating::RatingAlgorithm;
 use once_cell::sync::Lazy;
-use std::sync::Mutex;
-use pyo3::types::PyDict;
+use std::{fs, path::PathBuf, sync::Mutex};
+use pyo3::create_exception;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyList};
 use pyo3::Py;
 
-static GLOBAL_ELO_SYSTEM: Lazy<Mutex<EloSystem>> = Lazy::new(|| Mutex::new(EloSystem::new()));
+create_exception!(belo, BeloError, PyRuntimeError, "Base class of every error raised by belo.");
+create_exception!(belo, EmptyIdError, BeloError, "A player ID was empty.");
+create_exception!(belo, DuplicatePlayerError, BeloError, "A player with that ID already exists.");
+create_exception!(belo, UnknownPlayerError, BeloError, "No player has that ID.");
+create_exception!(belo, SelfMatchError, BeloError, "A player was matched against or merged into themselves.");
+create_exception!(belo, UnknownMatchError, BeloError, "No recorded match has that ID.");
+create_exception!(belo, NoActiveProjectError, BeloError, "No CLI project is active.");
+create_exception!(belo, UnknownProjectError, BeloError, "No CLI project has that name.");
+create_exception!(belo, DuplicateProjectError, BeloError, "A CLI project with that name already exists.");
+create_exception!(belo, UnknownSettingError, BeloError, "No setting has that name.");
+create_exception!(belo, InvalidSettingError, BeloError, "A setting was given a value outside its range.");
 
+/// Raises the exception class matching the error, or `BeloError` for any other failure.
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    let message = e.to_string();
+    match e.downcast_ref::<error::BeloError>() {
+        Some(error::BeloError::EmptyId) => EmptyIdError::new_err(message),
+        Some(error::BeloError::DuplicatePlayer(_)) => DuplicatePlayerError::new_err(message),
+        Some(error::BeloError::UnknownPlayer(_)) => UnknownPlayerError::new_err(message),
+        Some(error::BeloError::SelfMatch) => SelfMatchError::new_err(message),
+        Some(error::BeloError::UnknownMatch(_)) => UnknownMatchError::new_err(message),
+        Some(error::BeloError::NoActiveProject) => NoActiveProjectError::new_err(message),
+        Some(error::BeloError::UnknownProject(_)) => UnknownProjectError::new_err(message),
+        Some(error::BeloError::DuplicateProject(_)) => DuplicateProjectError::new_err(message),
+        Some(error::BeloError::UnknownSetting(_)) => UnknownSettingError::new_err(message),
+        Some(error::BeloError::InvalidSetting { .. }) => InvalidSettingError::new_err(message),
+        None => BeloError::new_err(message),
+    }
+}
+
+fn parse_algorithm(algorithm: &str) -> PyResult<RatingAlgorithm> {
+    RatingAlgorithm::from_str(algorithm, true).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+fn parse_result(result: &str) -> PyResult<MatchResult> {
+    result.parse().map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// A result given to `record_games`: a string as for `record_game`, or 1, -1 or 0 for a win
+/// of the first player, a win of the second player or a tie.
+fn extract_result(result: &Bound<'_, PyAny>) -> PyResult<MatchResult> {
+    if let Ok(result) = result.extract::<String>() {
+        return parse_result(&result);
+    }
+    let invalid = || {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Invalid result {}. Use a result string, or 1, -1 or 0.",
+            result
+        ))
+    };
+    // `bool` is an `int` to Python, but True isn't a meaningful result.
+    if result.is_instance_of::<PyBool>() {
+        return Err(invalid());
+    }
+    match result.extract::<i64>() {
+        Ok(1) => Ok(GameResult::Player1Wins.into()),
+        Ok(-1) => Ok(GameResult::Player2Wins.into()),
+        Ok(0) => Ok(GameResult::Tie.into()),
+        _ => Err(invalid()),
+    }
+}
+
+/// Zips the columns given to `record_games` into games, failing on the first invalid one.
+fn extract_games(
+    ids1: &Bound<'_, PyAny>,
+    results: &Bound<'_, PyAny>,
+    ids2: &Bound<'_, PyAny>,
+) -> PyResult<Vec<(String, MatchResult, String)>> {
+    let (len1, len, len2) = (ids1.len()?, results.len()?, ids2.len()?);
+    if len1 != len || len2 != len {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "ids1, results and ids2 must have the same length, got {}, {} and {}.",
+            len1, len, len2
+        )));
+    }
+    ids1.try_iter()?
+        .zip(results.try_iter()?)
+        .zip(ids2.try_iter()?)
+        .map(|((id1, result), id2)| Ok((id1?.extract()?, extract_result(&result?)?, id2?.extract()?)))
+        .collect()
+}
+
+/// Floats as a NumPy array, or as an `array.array` when NumPy isn't installed.
+fn float_array<'py>(py: Python<'py>, values: &[f64]) -> PyResult<Bound<'py, PyAny>> {
+    let bytes: Vec<u8> = values.iter().flat_map(|value| value.to_ne_bytes()).collect();
+    let array = py.import("array")?.call_method1("array", ("d",))?;
+    array.call_method1("frombytes", (PyBytes::new(py, &bytes),))?;
+    match py.import("numpy") {
+        Ok(numpy) => numpy.call_method1("frombuffer", (array, "float64")),
+        Err(_) => Ok(array),
+    }
+}
+
+/// A square matrix given row by row, as a 2-D NumPy array, or as a list of `array.array`
+/// rows when NumPy isn't installed.
+fn float_matrix<'py>(py: Python<'py>, values: &[f64], n: usize) -> PyResult<Bound<'py, PyAny>> {
+    if py.import("numpy").is_ok() {
+        return float_array(py, values)?.call_method1("reshape", ((n, n),));
+    }
+    let rows = values.chunks(n.max(1)).map(|row| float_array(py, row)).collect::<PyResult<Vec<_>>>()?;
+    Ok(PyList::new(py, rows)?.into_any())
+}
+
+/// The rating changes returned by `record_games`, as one array per side.
+fn delta_arrays<'py>(py: Python<'py>, deltas: &[(f64, f64)]) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>)> {
+    let (deltas1, deltas2): (Vec<f64>, Vec<f64>) = deltas.iter().copied().unzip();
+    Ok((float_array(py, &deltas1)?, float_array(py, &deltas2)?))
+}
+
+fn parse_sort(sort: &str) -> PyResult<SortBy> {
+    SortBy::from_str(sort, true).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// A player's standing as a dict.
+fn standing_dict(py: Python, player: LeaderboardRow) -> PyResult<Py<PyDict>> {
+    // Algorithms that don't track a deviation or volatility report them as zero.
+    let deviation = player.deviation.unwrap_or(0.0);
+    let (ci_lower, ci_upper) = player.confidence_interval().unwrap_or((player.rating, player.rating));
+    let d = PyDict::new(py);
+    d.set_item("rank", player.rank)?;
+    d.set_item("id", player.id)?;
+    d.set_item("elo", player.rating)?;
+    d.set_item("deviation", deviation)?;
+    d.set_item("volatility", player.volatility.unwrap_or(0.0))?;
+    d.set_item("ci_lower", ci_lower)?;
+    d.set_item("ci_upper", ci_upper)?;
+    d.set_item("conservative", player.rating - 2.0 * deviation)?;
+    d.set_item("wins", player.wins)?;
+    d.set_item("losses", player.losses)?;
+    d.set_item("ties", player.ties)?;
+    d.set_item("games", player.games)?;
+    d.set_item("points_for", player.points_for)?;
+    d.set_item("points_against", player.points_against)?;
+    d.set_item("last_played", player.last_played.map(|at| at.to_rfc3339()))?;
+    Ok(d.into())
+}
+
+/// A leaderboard of its own. Create one per task to keep several apart in one process.
+#[pyclass(name = "EloSystem", module = "belo")]
+struct PyEloSystem {
+    inner: EloSystem,
+    /// The CLI project it was opened from, if any.
+    project: Option<String>,
+}
+
+impl PyEloSystem {
+    fn open(name: &str) -> PyResult<Self> {
+        let dir = project::project_dir(name).map_err(to_py_err)?;
+        let inner = project::load(&dir).map_err(to_py_err)?;
+        Ok(Self { inner, project: Some(name.to_string()) })
+    }
+}
+
+#[pymethods]
+impl PyEloSystem {
+    #[new]
+    #[pyo3(signature = (algorithm="glicko2"))]
+    fn new(algorithm: &str) -> PyResult<Self> {
+        Ok(Self { inner: EloSystem::with_algorithm(parse_algorithm(algorithm)?), project: None })
+    }
+
+    /// Name of the CLI project it was opened from, or None.
+    #[getter]
+    fn project(&self) -> Option<String> {
+        self.project.clone()
+    }
+
+    /// Writes it to a JSON file at `path`, or back to the CLI project it was opened from.
+    #[pyo3(signature = (path=None))]
+    fn save(&mut self, path: Option<PathBuf>) -> PyResult<()> {
+        if let Some(path) = path {
+            let json = self.inner.to_json().map_err(to_py_err)?;
+            return fs::write(&path, json)
+                .map_err(|e| BeloError::new_err(format!("Failed to write Elo system to '{:?}': {}", path, e)));
+        }
+        let name = self.project.as_deref().ok_or_else(|| {
+            BeloError::new_err("This EloSystem wasn't opened from a project; pass a path or use belo.open_project().")
+        })?;
+        let dir = project::project_dir(name).map_err(to_py_err)?;
+        project::save(&dir, &mut self.inner).map_err(to_py_err)
+    }
+
+    /// Reads a file written by `save(path)`.
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        let json = fs::read_to_string(&path)
+            .map_err(|e| BeloError::new_err(format!("Failed to read Elo system from '{:?}': {}", path, e)))?;
+        Self::from_json(&json)
+    }
+
+    /// The players, settings and match history as a JSON string.
+    fn to_json(&self) -> PyResult<String> {
+        self.inner.to_json().map_err(to_py_err)
+    }
+
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self { inner: EloSystem::from_json(json).map_err(to_py_err)?, project: None })
+    }
+
+    // Pickling goes through JSON. An unpickled copy isn't tied to a project, so workers
+    // can't overwrite it by accident.
+    fn __getstate__(&self) -> PyResult<String> {
+        self.to_json()
+    }
+
+    fn __setstate__(&mut self, state: &str) -> PyResult<()> {
+        *self = Self::from_json(state)?;
+        Ok(())
+    }
+
+    /// The rating algorithm, e.g. "glicko2".
+    #[getter]
+    fn algorithm(&self) -> String {
+        self.inner.algorithm().to_string()
+    }
+
+    fn add_player(&mut self, id: &str) -> PyResult<()> {
+        self.inner.add_player(id).map_err(to_py_err)
+    }
+
+    fn remove_player(&mut self, id: &str) -> PyResult<()> {
+        self.inner.remove_player(id).map_err(to_py_err)
+    }
+
+    fn rename_player(&mut self, old: &str, new: &str) -> PyResult<()> {
+        self.inner.rename_player(old, new).map_err(to_py_err)
+    }
+
+    fn merge_players(&mut self, duplicate: &str, into: &str) -> PyResult<()> {
+        self.inner.merge_players(duplicate, into).map_err(to_py_err)
+    }
+
+    fn record_game(&mut self, id1: &str, result: &str, id2: &str) -> PyResult<()> {
+        let game_result = parse_result(result)?;
+        self.inner.record_game(id1, game_result, id2).map_err(to_py_err)
+    }
+
+    /// Records many games in one call, with the GIL released while they are rated.
+    ///
+    /// Takes three equally long sequences or NumPy arrays and returns how much every game
+    /// moved the ratings of its first and second player.
+    fn record_games<'py>(
+        &mut self,
+        py: Python<'py>,
+        ids1: &Bound<'py, PyAny>,
+        results: &Bound<'py, PyAny>,
+        ids2: &Bound<'py, PyAny>,
+    ) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>)> {
+        let games = extract_games(ids1, results, ids2)?;
+        let deltas = py.allow_threads(|| self.inner.record_games(&games)).map_err(to_py_err)?;
+        delta_arrays(py, &deltas)
+    }
+
+    #[pyo3(signature = (id1, id2, results, single_period=false))]
+    fn record_series(&mut self, id1: &str, id2: &str, results: Vec<String>, single_period: bool) -> PyResult<()> {
+        let results = results
+            .iter()
+            .map(|result| parse_result(result))
+            .collect::<PyResult<Vec<_>>>()?;
+        self.inner.record_series(id1, id2, &results, single_period).map_err(to_py_err)
+    }
+
+    fn record_team_game(&mut self, team1: Vec<String>, result: &str, team2: Vec<String>) -> PyResult<()> {
+        let game_result = parse_result(result)?;
+        self.inner.record_team_game(&team1, game_result, &team2).map_err(to_py_err)
+    }
+
+    fn record_ranking(&mut self, ranking: &Bound<'_, PyAny>) -> PyResult<()> {
+        // Either "a > b = c > d", or a list best first where a nested list groups tied players.
+        let ranking = if let Ok(ranking) = ranking.extract::<String>() {
+            parse_ranking(&ranking).map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?
+        } else {
+            ranking
+                .extract::<Vec<Bound<'_, PyAny>>>()?
+                .into_iter()
+                .map(|group| match group.extract::<String>() {
+                    Ok(id) => Ok(vec![id]),
+                    Err(_) => group.extract::<Vec<String>>(),
+                })
+                .collect::<PyResult<Vec<_>>>()?
+        };
+        self.inner.record_ranking(&ranking).map_err(to_py_err)
+    }
+
+    /// The best `n` players as dicts, best first.
+    #[pyo3(signature = (n=5, sort="rating"))]
+    fn top(&self, py: Python, n: Option<usize>, sort: &str) -> PyResult<Vec<Py<PyDict>>> {
+        let sort = parse_sort(sort)?;
+        self.inner.get_top_n(n, sort).into_iter().map(|player| standing_dict(py, player)).collect()
+    }
+
+    /// Every player's standing as one column per key, with the float columns as NumPy arrays,
+    /// so that it can be passed straight to `pandas.DataFrame`.
+    #[pyo3(signature = (sort="rating"))]
+    fn leaderboard(&self, py: Python, sort: &str) -> PyResult<Py<PyDict>> {
+        let rows = self.inner.leaderboard(parse_sort(sort)?);
+        let float_column =
+            |value: fn(&LeaderboardRow) -> f64| float_array(py, &rows.iter().map(value).collect::<Vec<_>>());
+        let count_column = |value: fn(&LeaderboardRow) -> u32| rows.iter().map(value).collect::<Vec<_>>();
+        let columns = PyDict::new(py);
+        columns.set_item("rank", rows.iter().map(|row| row.rank).collect::<Vec<_>>())?;
+        columns.set_item("id", rows.iter().map(|row| row.id.as_str()).collect::<Vec<_>>())?;
+        columns.set_item("rating", float_column(|row| row.rating)?)?;
+        // Algorithms that don't track a deviation or volatility report them as zero, as in `top`.
+        columns.set_item("deviation", float_column(|row| row.deviation.unwrap_or(0.0))?)?;
+        columns.set_item("volatility", float_column(|row| row.volatility.unwrap_or(0.0))?)?;
+        columns.set_item("ci_lower", float_column(|row| row.confidence_interval().map_or(row.rating, |ci| ci.0))?)?;
+        columns.set_item("ci_upper", float_column(|row| row.confidence_interval().map_or(row.rating, |ci| ci.1))?)?;
+        columns.set_item("conservative", float_column(|row| row.rating - 2.0 * row.deviation.unwrap_or(0.0))?)?;
+        columns.set_item("wins", count_column(|row| row.wins))?;
+        columns.set_item("losses", count_column(|row| row.losses))?;
+        columns.set_item("ties", count_column(|row| row.ties))?;
+        columns.set_item("games", count_column(|row| row.games))?;
+        columns.set_item("points_for", count_column(|row| row.points_for))?;
+        columns.set_item("points_against", count_column(|row| row.points_against))?;
+        let last_played: Vec<Option<String>> =
+            rows.iter().map(|row| row.last_played.map(|at| at.to_rfc3339())).collect();
+        columns.set_item("last_played", last_played)?;
+        Ok(columns.into())
+    }
+
+    /// Every player's chance of beating every other as an N×N NumPy array, with players in
+    /// the order of `leaderboard(sort)` and NaN on the diagonal.
+    #[pyo3(signature = (sort="rating"))]
+    fn pairwise_win_probabilities<'py>(&self, py: Python<'py>, sort: &str) -> PyResult<Bound<'py, PyAny>> {
+        let probabilities = self.inner.win_probabilities(parse_sort(sort)?);
+        float_matrix(py, &probabilities, self.inner.player_count())
+    }
+
+    /// A single player's standing as a dict.
+    fn info(&self, py: Python, id: &str) -> PyResult<Py<PyDict>> {
+        let player = self
+            .inner
+            .standing(id)
+            .ok_or_else(|| to_py_err(error::BeloError::UnknownPlayer(id.to_string()).into()))?;
+        standing_dict(py, player)
+    }
+
+    fn predict(&self, py: Python, id1: &str, id2: &str) -> PyResult<Py<PyDict>> {
+        let prediction = self.inner.predict(id1, id2).map_err(to_py_err)?;
+
+        let d = PyDict::new(py);
+        d.set_item("win", prediction.win)?;
+        d.set_item("loss", prediction.loss)?;
+        d.set_item("draw", prediction.draw)?;
+        Ok(d.into())
+    }
+
+    /// Prints the best `n` players as a table.
+    #[pyo3(signature = (n=None, sort="rating"))]
+    fn head(&self, n: Option<usize>, sort: &str) -> PyResult<()> {
+        let sort = parse_sort(sort)?;
+        self.inner.print_top(n, sort);
+        Ok(())
+    }
+
+    fn print_info(&self, id: &str) -> PyResult<()> {
+        self.inner.print_info(id).map_err(to_py_err)
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.player_count()
+    }
+
+    fn __repr__(&self) -> String {
+        let project = self.project.as_ref().map_or(String::new(), |name| format!("project='{}', ", name));
+        format!(
+            "EloSystem({}algorithm='{}', players={})",
+            project,
+            self.inner.algorithm(),
+            self.inner.player_count()
+        )
+    }
+}
+
+/// Opens a project created with the CLI, e.g. to analyse it in a notebook.
+///
+/// Changes stay in memory until `save()` is called.
+#[pyfunction]
+fn open_project(name: &str) -> PyResult<PyEloSystem> {
+    PyEloSystem::open(name)
+}
+
+/// Opens the project activated with `belo activate`.
+#[pyfunction]
+fn active_project() -> PyResult<PyEloSystem> {
+    PyEloSystem::open(&project::active_project().map_err(to_py_err)?)
+}
+
+// The module-level functions below work on one shared leaderboard, as they always have.
+
+static GLOBAL_ELO_SYSTEM: Lazy<Mutex<PyEloSystem>> =
+    Lazy::new(|| Mutex::new(PyEloSystem { inner: EloSystem::new(), project: None }));
+
+#[pyfunction]
+#[pyo3(signature = (algorithm="glicko2"))]
+fn init_state(algorithm: &str) -> PyResult<()> {
+    let sys = PyEloSystem::new(algorithm)?;
+    *GLOBAL_ELO_SYSTEM.lock().unwrap() = sys;
+    Ok(())
+}
+
+/// Writes the shared leaderboard to a JSON file.
+#[pyfunction]
+fn save_state(path: PathBuf) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().save(Some(path))
+}
+
+/// Replaces the shared leaderboard with one written by `save_state` or `EloSystem.save`.
 #[pyfunction]
-fn init_state() -> PyResult<()> {
-    let mut sys = GLOBAL_ELO_SYSTEM.lock().unwrap();
-    *sys = EloSystem::new();
+fn load_state(path: PathBuf) -> PyResult<()> {
+    let sys = PyEloSystem::load(path)?;
+    *GLOBAL_ELO_SYSTEM.lock().unwrap() = sys;
     Ok(())
 }
 
 #[pyfunction]
 fn add_player(id: &str) -> PyResult<()> {
-    let mut sys = GLOBAL_ELO_SYSTEM.lock().unwrap();
-    sys.add_player(id)
-       .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
+    GLOBAL_ELO_SYSTEM.lock().unwrap().add_player(id)
+}
+
+#[pyfunction]
+fn remove_player(id: &str) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().remove_player(id)
+}
+
+#[pyfunction]
+fn rename_player(old: &str, new: &str) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().rename_player(old, new)
+}
+
+#[pyfunction]
+fn merge_players(duplicate: &str, into: &str) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().merge_players(duplicate, into)
 }
 
 #[pyfunction]
 fn record_game(id1: &str, result: &str, id2: &str) -> PyResult<()> {
-    let game_result = match result {
-        "gt" | ">"  => GameResult::Player1Wins,
-        "lt" | "<"  => GameResult::Player2Wins,
-        "eq" | "==" => GameResult::Tie,
-        _ => return Err(pyo3::exceptions::PyValueError::new_err("Invalid game result")),
-    };
-    let mut sys = GLOBAL_ELO_SYSTEM.lock().unwrap();
-    sys.record_game(id1, game_result, id2)
-       .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
+    GLOBAL_ELO_SYSTEM.lock().unwrap().record_game(id1, result, id2)
 }
 
 #[pyfunction]
-#[pyo3(signature = (n=5))]
-fn get_top_n(n: Option<usize>, py: Python) -> PyResult<Vec<Py<PyDict>>> {
-    let sys = GLOBAL_ELO_SYSTEM.lock().unwrap();
-    let top = sys.get_top_n(n);
+fn record_games<'py>(
+    py: Python<'py>,
+    ids1: &Bound<'py, PyAny>,
+    results: &Bound<'py, PyAny>,
+    ids2: &Bound<'py, PyAny>,
+) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>)> {
+    let games = extract_games(ids1, results, ids2)?;
+    // The lock is taken and released without the GIL, so a thread holding the GIL while
+    // waiting for the lock can't deadlock with this one.
+    let deltas = py
+        .allow_threads(|| GLOBAL_ELO_SYSTEM.lock().unwrap().inner.record_games(&games))
+        .map_err(to_py_err)?;
+    delta_arrays(py, &deltas)
+}
 
-    let mut result = Vec::with_capacity(top.len());
-    for (id, elo, wins, losses, ties) in top {
-        let d = PyDict::new(py);
-        d.set_item("id", id)?;
-        d.set_item("elo", elo)?;
-        d.set_item("wins", wins)?;
-        d.set_item("losses", losses)?;
-        d.set_item("ties", ties)?;
-        result.push(d.into());
-    }
-    Ok(result)
+#[pyfunction]
+#[pyo3(signature = (id1, id2, results, single_period=false))]
+fn record_series(id1: &str, id2: &str, results: Vec<String>, single_period: bool) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().record_series(id1, id2, results, single_period)
+}
+
+#[pyfunction]
+fn record_team_game(team1: Vec<String>, result: &str, team2: Vec<String>) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().record_team_game(team1, result, team2)
+}
+
+#[pyfunction]
+fn record_ranking(ranking: &Bound<'_, PyAny>) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().record_ranking(ranking)
+}
+
+#[pyfunction]
+#[pyo3(signature = (n=5, sort="rating"))]
+fn get_top_n(n: Option<usize>, sort: &str, py: Python) -> PyResult<Vec<Py<PyDict>>> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().top(py, n, sort)
+}
+
+// Named so that it doesn't clash with the `leaderboard` module.
+#[pyfunction]
+#[pyo3(name = "leaderboard", signature = (sort="rating"))]
+fn global_leaderboard(py: Python, sort: &str) -> PyResult<Py<PyDict>> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().leaderboard(py, sort)
+}
+
+#[pyfunction]
+#[pyo3(signature = (sort="rating"))]
+fn pairwise_win_probabilities<'py>(py: Python<'py>, sort: &str) -> PyResult<Bound<'py, PyAny>> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().pairwise_win_probabilities(py, sort)
+}
+
+#[pyfunction]
+fn predict(id1: &str, id2: &str, py: Python) -> PyResult<Py<PyDict>> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().predict(py, id1, id2)
 }
 
 #[pyfunction]
-#[pyo3(signature = (n=None))]
-fn head(n: Option<usize>) {
-    let sys = GLOBAL_ELO_SYSTEM.lock().unwrap();
-    sys.print_top(n);
+#[pyo3(signature = (n=None, sort="rating"))]
+fn head(n: Option<usize>, sort: &str) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().head(n, sort)
 }
 
 #[pyfunction]
-fn print_info(id: &str) {
-    let sys = GLOBAL_ELO_SYSTEM.lock().unwrap();
-    sys.print_info(id);
+fn print_info(id: &str) -> PyResult<()> {
+    GLOBAL_ELO_SYSTEM.lock().unwrap().print_info(id)
 }
 
 #[pymodule]
 fn belo(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyEloSystem>()?;
+    m.add_function(wrap_pyfunction!(open_project, m)?)?;
+    m.add_function(wrap_pyfunction!(active_project, m)?)?;
     m.add_function(wrap_pyfunction!(init_state, m)?)?;
+    m.add_function(wrap_pyfunction!(save_state, m)?)?;
+    m.add_function(wrap_pyfunction!(load_state, m)?)?;
     m.add_function(wrap_pyfunction!(add_player, m)?)?;
+    m.add_function(wrap_pyfunction!(remove_player, m)?)?;
+    m.add_function(wrap_pyfunction!(rename_player, m)?)?;
+    m.add_function(wrap_pyfunction!(merge_players, m)?)?;
     m.add_function(wrap_pyfunction!(record_game, m)?)?;
+    m.add_function(wrap_pyfunction!(record_games, m)?)?;
+    m.add_function(wrap_pyfunction!(record_series, m)?)?;
+    m.add_function(wrap_pyfunction!(record_team_game, m)?)?;
+    m.add_function(wrap_pyfunction!(record_ranking, m)?)?;
     m.add_function(wrap_pyfunction!(get_top_n, m)?)?;
+    m.add_function(wrap_pyfunction!(global_leaderboard, m)?)?;
+    m.add_function(wrap_pyfunction!(pairwise_win_probabilities, m)?)?;
+    m.add_function(wrap_pyfunction!(predict, m)?)?;
     m.add_function(wrap_pyfunction!(head, m)?)?;
     m.add_function(wrap_pyfunction!(print_info, m)?)?;
+    m.add("BeloError", m.py().get_type::<BeloError>())?;
+    m.add("EmptyIdError", m.py().get_type::<EmptyIdError>())?;
+    m.add("DuplicatePlayerError", m.py().get_type::<DuplicatePlayerError>())?;
+    m.add("UnknownPlayerError", m.py().get_type::<UnknownPlayerError>())?;
+    m.add("SelfMatchError", m.py().get_type::<SelfMatchError>())?;
+    m.add("UnknownMatchError", m.py().get_type::<UnknownMatchError>())?;
+    m.add("NoActiveProjectError", m.py().get_type::<NoActiveProjectError>())?;
+    m.add("UnknownProjectError", m.py().get_type::<UnknownProjectError>())?;
+    m.add("DuplicateProjectError", m.py().get_type::<DuplicateProjectError>())?;
+    m.add("UnknownSettingError", m.py().get_type::<UnknownSettingError>())?;
+    m.add("InvalidSettingError", m.py().get_type::<InvalidSettingError>())?;
     Ok(())
 }
\ No newline at end of file
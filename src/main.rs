@@ -1,208 +1,539 @@
 mod cli;
-mod config;
-mod elo_system;
 
-use cli::{Cli, Commands, VsResult};
-use config::Config;
-use elo_system::{EloSystem, GameResult};
-
-use anyhow::{anyhow, Context, Result};
+use belo::config::Config;
+use belo::elo_system::{EloSystem, GameResult, MatchResult, SortBy};
+use belo::error::BeloError;
+use belo::history::{parse_ranking, MatchOutput, MatchRecord};
+use belo::import::{ColumnMap, ImportFormat};
+use belo::leaderboard::ExportFormat;
+use belo::output::{message, OutputFormat};
+use belo::rating::RatingAlgorithm;
+use belo::settings::SETTING_KEYS;
+use belo::{import, leaderboard, output, project};
+use cli::{Cli, Commands, ConfigAction, MatchAction, PeriodAction};
+
+use anyhow::{Context, Result};
 use clap::{Parser};
-use dirs::home_dir;
+use serde_json::{json, Value};
 use std::{
-    fs::{self, File},
-    io::{Read, Write},
-    path::PathBuf,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
 
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    output::set_format(cli.format);
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let kind = e.downcast_ref::<BeloError>();
+            if output::is_json() {
+                let error = json!({
+                    "error": {
+                        "kind": kind.map_or("error", BeloError::kind),
+                        "message": format!("{:#}", e),
+                    }
+                });
+                // Scripts still get a JSON document on stdout; ignore a closed stdout here.
+                let _ = output::print_json(&error);
+            }
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(kind.map_or(1, BeloError::exit_code))
+        }
+    }
+}
 
-    match cli.command {
-        Commands::Init { project_name } => init_project(&project_name)?,
+fn run(cli: Cli) -> Result<()> {
+    let result = match cli.command {
+        Commands::Init { project_name, algorithm } => init_project(&project_name, algorithm)?,
         Commands::Activate { project_name } => activate_project(&project_name)?,
         Commands::List => list_projects()?,
         Commands::Whoami => whoami()?,
         Commands::Deactivate => deactivate()?,
         Commands::Delete { project_name } => delete_project(&project_name)?,
-        Commands::Head { count } => {
+        Commands::Head { count, sort, bootstrap, seed, fit } => {
             let elo_system = load_active_project()?;
-            elo_system.print_top(count);
+            match bootstrap {
+                Some(samples) if output::is_json() => {
+                    let mut players = elo_system.bootstrap(samples, seed, fit)?;
+                    players.truncate(count.unwrap_or(5));
+                    json!({
+                        "samples": samples,
+                        "seed": seed,
+                        "model": fit,
+                        "matches": elo_system.history().len(),
+                        "players": players,
+                    })
+                }
+                Some(samples) => {
+                    elo_system.print_bootstrap(count, samples, seed, fit)?;
+                    Value::Null
+                }
+                None if output::is_json() => json!({ "players": elo_system.get_top_n(count, sort) }),
+                None => {
+                    elo_system.print_top(count, sort);
+                    Value::Null
+                }
+            }
+        }
+        Commands::Export { to, output, sort } => {
+            let format = to.unwrap_or(match cli.format {
+                OutputFormat::Text => ExportFormat::Csv,
+                OutputFormat::Json => ExportFormat::Json,
+            });
+            export(format, output.as_deref(), sort)?
         }
         Commands::Add { id } => {
             let mut elo_system = load_active_project()?;
             elo_system.add_player(&id)?;
-            save_active_project(&elo_system)?;
+            save_active_project(&mut elo_system)?;
+            json!({ "player": elo_system.standing(&id) })
         }
-        Commands::Vs { id1, result, id2 } => {
+        Commands::Remove { id } => {
             let mut elo_system = load_active_project()?;
-            let game_result = match result {
-                VsResult::Gt => GameResult::Player1Wins,
-                VsResult::Lt => GameResult::Player2Wins,
-                VsResult::Eq => GameResult::Tie,
-            };
-
-            elo_system.record_game(&id1, game_result, &id2)?;
-            save_active_project(&elo_system)?;
+            elo_system.remove_player(&id)?;
+            save_active_project(&mut elo_system)?;
+            json!({ "removed": id })
+        }
+        Commands::Rename { old, new } => {
+            let mut elo_system = load_active_project()?;
+            elo_system.rename_player(&old, &new)?;
+            save_active_project(&mut elo_system)?;
+            json!({ "renamed": old, "player": elo_system.standing(&new) })
+        }
+        Commands::Merge { duplicate, into } => {
+            let mut elo_system = load_active_project()?;
+            elo_system.merge_players(&duplicate, &into)?;
+            save_active_project(&mut elo_system)?;
+            json!({ "merged": duplicate, "player": elo_system.standing(&into) })
+        }
+        Commands::Vs { id1, result, id2 } => {
+            record_matches(|elo_system| elo_system.record_game(&id1, result, &id2))?
+        }
+        Commands::Series { id1, id2, results, single_period } => {
+            record_matches(|elo_system| elo_system.record_series(&id1, &id2, &results, single_period))?
+        }
+        Commands::TeamVs { team1, result, team2 } => record_matches(|elo_system| {
+            elo_system.record_team_game(&parse_team(&team1), result, &parse_team(&team2))
+        })?,
+        Commands::Rank { ranking } => {
+            let ranking = parse_ranking(&ranking.join(" "))?;
+            record_matches(|elo_system| elo_system.record_ranking(&ranking))?
+        }
+        Commands::Import { path, from, columns, create_players, dry_run } => {
+            import_matches(&path, from, &columns, create_players, dry_run)?
         }
+        Commands::Fit { count, model } => {
+            let elo_system = load_active_project()?;
+            if output::is_json() {
+                let (fit, mut players) = elo_system.fit(model)?;
+                players.truncate(count.unwrap_or(usize::MAX));
+                json!({
+                    "model": model,
+                    "matches": elo_system.history().len(),
+                    "iterations": fit.iterations,
+                    "tie_parameter": fit.tie_parameter,
+                    "players": players,
+                })
+            } else {
+                elo_system.print_fit(model, count)?;
+                Value::Null
+            }
+        }
+        Commands::Predict { id1, id2 } => predict(&id1, &id2)?,
         Commands::Info { id } => {
             let elo_system = load_active_project()?;
-            elo_system.print_info(&id);
+            if output::is_json() {
+                let player = elo_system.standing(&id).ok_or(BeloError::UnknownPlayer(id))?;
+                json!({ "player": player })
+            } else {
+                elo_system.print_info(&id)?;
+                Value::Null
+            }
         }
+        Commands::Undo { count } => undo(count.unwrap_or(1))?,
+        Commands::Match { action } => match action {
+            MatchAction::List { count } => list_matches(count.unwrap_or(10))?,
+            MatchAction::Edit { id, result } => edit_match(id, result)?,
+            MatchAction::Delete { id } => delete_match(id)?,
+        },
+        Commands::Period { action } => match action {
+            PeriodAction::Start => start_period()?,
+            PeriodAction::Close => close_period(false)?,
+            PeriodAction::Stop => close_period(true)?,
+            PeriodAction::Status => {
+                let elo_system = load_active_project()?;
+                if output::is_json() {
+                    serde_json::to_value(elo_system.period_status())?
+                } else {
+                    elo_system.print_period_status();
+                    Value::Null
+                }
+            }
+        },
+        Commands::Recompute { algorithm } => recompute(algorithm)?,
+        Commands::Config { action } => match action {
+            ConfigAction::Get { key } => get_setting(key.as_deref())?,
+            ConfigAction::Set { key, value } => set_setting(&key, value)?,
+        },
+    };
+
+    // Commands that print a table in text mode, or that wrote their output themselves, return null.
+    if output::is_json() && !result.is_null() {
+        output::print_json(&result)?;
     }
-
     Ok(())
 }
 
-fn init_project(project_name: &str) -> Result<()> {
-    let projects_dir = get_projects_dir()?;
-    let project_path = projects_dir.join(project_name);
+/// Splits a comma-separated list of team members, ignoring stray whitespace.
+fn parse_team(team: &str) -> Vec<String> {
+    team.split(',').map(str::trim).filter(|id| !id.is_empty()).map(String::from).collect()
+}
+
+/// Records matches in the active project, returning the matches that were added.
+fn record_matches(record: impl FnOnce(&mut EloSystem) -> Result<()>) -> Result<Value> {
+    let mut elo_system = load_active_project()?;
+    let recorded = elo_system.history().len();
+    record(&mut elo_system)?;
+    save_active_project(&mut elo_system)?;
+    Ok(json!({ "matches": output_matches(&elo_system.history()[recorded..]) }))
+}
+
+fn init_project(project_name: &str, algorithm: RatingAlgorithm) -> Result<Value> {
+    let project_path = project::projects_dir()?.join(project_name);
 
     if project_path.exists() {
-        return Err(anyhow!("Project '{}' already exists.", project_name));
+        return Err(BeloError::DuplicateProject(project_name.to_string()).into());
     }
 
-    fs::create_dir_all(&project_path)
-        .with_context(|| format!("Failed to create project directory at '{:?}'", project_path))?;
-    let elo_system = EloSystem::new();
-    let elo_file = project_path.join("elo_data.json");
-    let serialized = serde_json::to_string_pretty(&elo_system)?;
-    let mut file = File::create(&elo_file)
-        .with_context(|| format!("Failed to create Elo data file at '{:?}'", elo_file))?;
-    file.write_all(serialized.as_bytes())?;
+    project::save(&project_path, &mut EloSystem::with_algorithm(algorithm))?;
 
-    println!("Project '{}' has been initialized.", project_name);
+    message!("Project '{}' has been initialized with {}.", project_name, algorithm);
     // Activate the project after initialization
     activate_project(project_name)?;
-    Ok(())
+    Ok(json!({ "project": project_name, "algorithm": algorithm, "active_project": project_name }))
 }
 
-fn activate_project(project_name: &str) -> Result<()> {
-    let projects_dir = get_projects_dir()?;
-    let project_path = projects_dir.join(project_name);
-
-    if !project_path.exists() {
-        return Err(anyhow!("Project '{}' does not exist.", project_name));
-    }
+fn activate_project(project_name: &str) -> Result<Value> {
+    project::project_dir(project_name)?;
 
     let mut config = Config::load()?;
     config.active_project = Some(project_name.to_string());
     config.save()?;
 
-    println!("Project '{}' has been activated.", project_name);
-    Ok(())
+    message!("Project '{}' has been activated.", project_name);
+    Ok(json!({ "active_project": project_name }))
 }
 
-fn list_projects() -> Result<()> {
-    let projects_dir = get_projects_dir()?;
+fn list_projects() -> Result<Value> {
+    let projects_dir = project::projects_dir()?;
+    let active_project = Config::load()?.active_project;
 
     if !projects_dir.exists() {
-        println!("No projects found.");
-        return Ok(());
+        message!("No projects found.");
+        return Ok(json!({ "projects": [], "active_project": active_project }));
     }
 
-    let projects: Vec<_> = fs::read_dir(&projects_dir)?
+    let entries: Vec<(String, bool)> = fs::read_dir(&projects_dir)?
         .filter_map(|entry| {
-            entry.ok().and_then(|e| {
-                e.file_name().into_string().ok().map(|s| {
-                    if e.path().is_dir() {
-                        s
-                    } else {
-                        format!("{} (file)", s)
-                    }
-                })
-            })
+            entry.ok().and_then(|e| e.file_name().into_string().ok().map(|s| (s, e.path().is_dir())))
         })
         .collect();
 
-    if projects.is_empty() {
+    if output::is_json() {
+        let projects: Vec<&String> = entries.iter().filter(|(_, is_dir)| *is_dir).map(|(name, _)| name).collect();
+        return Ok(json!({ "projects": projects, "active_project": active_project }));
+    }
+    if entries.is_empty() {
         println!("No projects found.");
     } else {
         println!("Projects:");
-        for project in projects {
-            println!("  - {}", project);
+        for (name, is_dir) in entries {
+            if is_dir {
+                println!("  - {}", name);
+            } else {
+                println!("  - {} (file)", name);
+            }
         }
     }
 
-    Ok(())
+    Ok(Value::Null)
 }
 
-fn whoami() -> Result<()> {
+fn whoami() -> Result<Value> {
     let config = Config::load()?;
     if let Some(project_name) = &config.active_project {
-        println!("Active project: {}", project_name);
+        message!("Active project: {}", project_name);
     } else {
-        println!("No active project.");
+        message!("No active project.");
     }
 
-    Ok(())
+    Ok(json!({ "active_project": config.active_project }))
 }
 
-fn deactivate() -> Result<()> {
+fn deactivate() -> Result<Value> {
     let mut config = Config::load()?;
-    if config.active_project.is_none() {
-        println!("No active project to deactivate.");
+    let deactivated = config.active_project.take();
+    if deactivated.is_none() {
+        message!("No active project to deactivate.");
     } else {
-        config.active_project = None;
         config.save()?;
-        println!("Project has been deactivated.");
+        message!("Project has been deactivated.");
     }
-    Ok(())
+    Ok(json!({ "deactivated": deactivated }))
 }
 
-fn delete_project(project_name: &str) -> Result<()> {
+fn delete_project(project_name: &str) -> Result<Value> {
     // First deactivate the project if it's active
     deactivate()?;
-    let projects_dir = get_projects_dir()?;
-    let project_path = projects_dir.join(project_name);
-
-    if !project_path.exists() {
-        return Err(anyhow!("Project '{}' does not exist.", project_name));
-    }
+    let project_path = project::project_dir(project_name)?;
 
     fs::remove_dir_all(&project_path)
         .with_context(|| format!("Failed to delete project directory at '{:?}'", project_path))?;
 
-    println!("Project '{}' has been deleted.", project_name);
-    Ok(())
+    message!("Project '{}' has been deleted.", project_name);
+    Ok(json!({ "deleted": project_name }))
 }
 
-fn load_active_project() -> Result<EloSystem> {
-    let config = Config::load()?;
-    let active = config.active_project.ok_or_else(|| anyhow!("No active project. Please activate a project first"))?;
+fn get_setting(key: Option<&str>) -> Result<Value> {
+    let settings = load_active_project()?.settings().clone();
+    let mut values = serde_json::Map::new();
+    match key {
+        Some(key) => {
+            let value = settings.get(key)?;
+            message!("{}", value);
+            values.insert(key.to_string(), json!(value));
+        }
+        None => {
+            for key in SETTING_KEYS {
+                let value = settings.get(key)?;
+                message!("{} = {}", key, value);
+                values.insert(key.to_string(), json!(value));
+            }
+        }
+    }
+    Ok(Value::Object(values))
+}
+
+fn set_setting(key: &str, value: f64) -> Result<Value> {
+    let mut elo_system = load_active_project()?;
+    let mut settings = elo_system.settings().clone();
+    let old = settings.get(key)?;
+    settings.set(key, value)?;
+    elo_system.set_settings(settings);
+    save_active_project(&mut elo_system)?;
+
+    message!("Setting '{}' changed: {} -> {}", key, old, value);
+    message!("Existing ratings are unchanged; run 'belo recompute' to apply it to past games.");
+    Ok(json!({ "key": key, "old": old, "new": value }))
+}
 
-    let elo_path = get_projects_dir()?.join(&active).join("elo_data.json");
+fn predict(id1: &str, id2: &str) -> Result<Value> {
+    let elo_system = load_active_project()?;
+    let prediction = elo_system.predict(id1, id2)?;
 
-    if !elo_path.exists() {
-        return Err(anyhow!("Elo data file not found for active project."));
+    message!("Prediction for {} vs {} ({}):", id1, id2, elo_system.algorithm());
+    message!("  P({} wins): {:.1}%", id1, prediction.win * 100.0);
+    message!("  P({} wins): {:.1}%", id2, prediction.loss * 100.0);
+    match prediction.draw {
+        Some(draw) => message!("  P(draw): {:.1}%", draw * 100.0),
+        None => message!("  (draws count as half a win; {} has no draw model)", elo_system.algorithm()),
     }
+    Ok(json!({
+        "player1": id1,
+        "player2": id2,
+        "algorithm": elo_system.algorithm(),
+        "player1_wins": prediction.win,
+        "player2_wins": prediction.loss,
+        "draw": prediction.draw,
+    }))
+}
 
-    let mut file = File::open(&elo_path)
-        .with_context(|| format!("Failed to open Elo data file at '{:?}'", elo_path))?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    let elo_system: EloSystem = serde_json::from_str(&contents)
-        .with_context(|| "Failed to parse Elo data file")?;
+fn undo(count: usize) -> Result<Value> {
+    let mut elo_system = load_active_project()?;
+    let removed = elo_system.undo(count)?;
+    save_active_project(&mut elo_system)?;
 
-    Ok(elo_system)
+    for record in removed.iter().rev() {
+        message!("Undid match {}", record);
+    }
+    Ok(json!({ "undone": output_matches(&removed) }))
 }
 
-fn save_active_project(elo_system: &EloSystem) -> Result<()> {
-    let config = Config::load()?;
-    let active = config.active_project.ok_or_else(|| anyhow!("No active project. Please activate a project first"))?;
+fn list_matches(count: usize) -> Result<Value> {
+    let elo_system = load_active_project()?;
+    if output::is_json() {
+        let history = elo_system.history();
+        return Ok(json!({ "matches": output_matches(&history[history.len().saturating_sub(count)..]) }));
+    }
+    elo_system.print_history(count);
+    Ok(Value::Null)
+}
 
-    let elo_path = get_projects_dir()?.join(&active).join("elo_data.json");
-    let serialized = serde_json::to_string_pretty(elo_system)?;
-    let mut file = File::create(&elo_path)
-        .with_context(|| format!("Failed to create Elo data file at '{:?}'", elo_path))?;
+fn edit_match(id: u64, result: MatchResult) -> Result<Value> {
+    let mut elo_system = load_active_project()?;
+    elo_system.edit_match(id, result)?;
+    save_active_project(&mut elo_system)?;
 
-    file.write_all(serialized.as_bytes())?;
-    Ok(())
+    message!("Match #{} changed to '{}' and later games replayed.", id, result);
+    Ok(json!({ "match": elo_system.history().iter().find(|record| record.id == id).map(MatchRecord::output) }))
+}
+
+fn delete_match(id: u64) -> Result<Value> {
+    let mut elo_system = load_active_project()?;
+    let removed = elo_system.delete_match(id)?;
+    save_active_project(&mut elo_system)?;
+
+    message!("Deleted match {} and replayed later games.", removed);
+    Ok(json!({ "deleted": removed.output() }))
+}
+
+/// Matches in the layout reported by `--format json`.
+fn output_matches(records: &[MatchRecord]) -> Vec<MatchOutput<'_>> {
+    records.iter().map(MatchRecord::output).collect()
+}
+
+fn import_matches(
+    path: &Path,
+    format: Option<ImportFormat>,
+    columns: &ColumnMap,
+    create_players: bool,
+    dry_run: bool,
+) -> Result<Value> {
+    let format = match format {
+        Some(format) => format,
+        None => ImportFormat::from_path(path)?,
+    };
+    let games = import::read_games(path, format, columns)?;
+    let mut elo_system = load_active_project()?;
+    let (games, duplicates) = elo_system.drop_duplicates(games);
+
+    let new_players = if dry_run {
+        elo_system.check_import(&games, create_players)?
+    } else {
+        let new_players = elo_system.import_games(&games, create_players)?;
+        save_active_project(&mut elo_system)?;
+        new_players
+    };
+
+    let count = |result: GameResult| games.iter().filter(|game| game.result.result == result).count();
+    let both_bad = games.iter().filter(|game| game.both_bad).count();
+    message!(
+        "{} {} games from {:?}: {} won by player 1, {} by player 2 and {} tied{}.",
+        if dry_run { "Would import" } else { "Imported" },
+        games.len(),
+        path,
+        count(GameResult::Player1Wins),
+        count(GameResult::Player2Wins),
+        count(GameResult::Tie),
+        if both_bad > 0 { format!(" ({} with both sides bad)", both_bad) } else { String::new() }
+    );
+    if duplicates > 0 {
+        message!("Skipped {} duplicate games.", duplicates);
+    }
+    let timestamps: Vec<_> = games.iter().filter_map(|game| game.played_at).collect();
+    let (first, last) = (timestamps.iter().min(), timestamps.iter().max());
+    if let (Some(first), Some(last)) = (first, last) {
+        message!(
+            "Played between {} and {}.",
+            first.format("%Y-%m-%d %H:%M:%S"),
+            last.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    if !new_players.is_empty() {
+        message!(
+            "{} {} new players: {}",
+            if dry_run { "Would add" } else { "Added" },
+            new_players.len(),
+            new_players.join(", ")
+        );
+    }
+    if dry_run {
+        message!("Dry run, nothing was changed.");
+    }
+    Ok(json!({
+        "dry_run": dry_run,
+        "imported": games.len(),
+        "player1_wins": count(GameResult::Player1Wins),
+        "player2_wins": count(GameResult::Player2Wins),
+        "ties": count(GameResult::Tie),
+        "both_bad": both_bad,
+        "duplicates": duplicates,
+        "first_played_at": first,
+        "last_played_at": last,
+        "new_players": new_players,
+    }))
+}
+
+fn export(format: ExportFormat, output: Option<&Path>, sort: SortBy) -> Result<Value> {
+    let elo_system = load_active_project()?;
+    let leaderboard = elo_system.leaderboard(sort);
+    let rendered = leaderboard::render(&leaderboard, format)?;
+
+    match output {
+        Some(path) => {
+            fs::write(path, rendered)
+                .with_context(|| format!("Failed to write leaderboard to '{:?}'", path))?;
+            message!("Exported {} players to {:?}.", leaderboard.len(), path);
+            Ok(json!({ "output": path, "players": leaderboard.len() }))
+        }
+        None => {
+            // The leaderboard itself is the output, whatever its format.
+            print!("{}", rendered);
+            Ok(Value::Null)
+        }
+    }
+}
+
+fn start_period() -> Result<Value> {
+    let mut elo_system = load_active_project()?;
+    let period = elo_system.start_period()?.clone();
+    save_active_project(&mut elo_system)?;
+
+    message!("Rating period #{} started; games are rated when it closes.", period.id);
+    Ok(json!({ "started": period }))
+}
+
+fn close_period(stop: bool) -> Result<Value> {
+    let mut elo_system = load_active_project()?;
+    let (period, matches) = elo_system.close_period(stop)?;
+    save_active_project(&mut elo_system)?;
+
+    message!("Rating period #{} closed, {} matches rated.", period.id, matches);
+    match elo_system.open_period() {
+        Some(next) => message!("Rating period #{} started.", next.id),
+        None => message!("Games are rated as they are recorded again."),
+    }
+    Ok(json!({ "closed": period, "matches_rated": matches, "started": elo_system.open_period() }))
 }
 
+fn recompute(algorithm: Option<RatingAlgorithm>) -> Result<Value> {
+    let mut elo_system = load_active_project()?;
+    if let Some(algorithm) = algorithm {
+        if algorithm != elo_system.algorithm() {
+            elo_system.set_algorithm(algorithm);
+            message!("Switched to {} with its default rating and deviation.", algorithm);
+        }
+    }
+
+    let count = elo_system.recompute()?;
+    save_active_project(&mut elo_system)?;
 
-fn get_projects_dir() -> Result<PathBuf> {
-    let home = home_dir().ok_or_else(|| anyhow!("Failed to get home directory"))?;
-    Ok(home.join(".cache").join("belo"))
-}
\ No newline at end of file
+    message!("Recomputed all ratings from {} matches.", count);
+    Ok(json!({ "algorithm": elo_system.algorithm(), "matches": count }))
+}
+
+fn load_active_project() -> Result<EloSystem> {
+    project::load(&get_active_project_dir()?)
+}
+
+fn save_active_project(elo_system: &mut EloSystem) -> Result<()> {
+    project::save(&get_active_project_dir()?, elo_system)
+}
+
+fn get_active_project_dir() -> Result<PathBuf> {
+    project::project_dir(&project::active_project()?)
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// What the CLI writes to stdout.
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Messages and tables for people
+    #[default]
+    Text,
+    /// One JSON document per command, for scripts; messages go to stderr
+    Json,
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Switches the whole process to the given output format.
+pub fn set_format(format: OutputFormat) {
+    JSON.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+/// Whether stdout is reserved for a command's JSON result.
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Prints a command's result on stdout.
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Prints a message for people: on stdout, or on stderr while stdout carries JSON.
+#[macro_export]
+macro_rules! message {
+    ($($arg:tt)*) => {
+        if $crate::output::is_json() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+pub use message;
@@ -0,0 +1,90 @@
+use anyhow::{anyhow, Context, Result};
+use dirs::home_dir;
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::config::Config;
+use crate::elo_system::EloSystem;
+use crate::error::BeloError;
+use crate::history::{append_history, load_history, save_history};
+use crate::settings::Settings;
+
+/// Directory holding every project.
+pub fn projects_dir() -> Result<PathBuf> {
+    let home = home_dir().ok_or_else(|| anyhow!("Failed to get home directory"))?;
+    Ok(home.join(".cache").join("belo"))
+}
+
+/// Directory of the named project, which must exist.
+pub fn project_dir(name: &str) -> Result<PathBuf> {
+    let dir = projects_dir()?.join(name);
+    if !dir.exists() {
+        return Err(BeloError::UnknownProject(name.to_string()).into());
+    }
+    Ok(dir)
+}
+
+/// Name of the project activated with `belo activate`.
+pub fn active_project() -> Result<String> {
+    Ok(Config::load()?.active_project.ok_or(BeloError::NoActiveProject)?)
+}
+
+/// Loads a project's ratings together with its settings and match history.
+pub fn load(project_dir: &Path) -> Result<EloSystem> {
+    let elo_path = project_dir.join("elo_data.json");
+
+    if !elo_path.exists() {
+        return Err(anyhow!("Elo data file not found at '{:?}'.", elo_path));
+    }
+
+    let mut file = File::open(&elo_path)
+        .with_context(|| format!("Failed to open Elo data file at '{:?}'", elo_path))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let mut elo_system: EloSystem = serde_json::from_str(&contents)
+        .with_context(|| "Failed to parse Elo data file")?;
+    let settings = Settings::load(&project_dir.join("settings.json"), elo_system.algorithm())?;
+    elo_system.set_settings(settings);
+    elo_system.set_history(load_history(&project_dir.join("history.jsonl"))?);
+    elo_system.mark_history_saved();
+
+    Ok(elo_system)
+}
+
+/// Saves a project's ratings, settings and match history, creating its directory if needed.
+///
+/// Matches recorded since the project was loaded are appended to the history; it is only
+/// rewritten when older matches changed.
+pub fn save(project_dir: &Path, elo_system: &mut EloSystem) -> Result<()> {
+    fs::create_dir_all(project_dir)
+        .with_context(|| format!("Failed to create project directory at '{:?}'", project_dir))?;
+    let history_path = project_dir.join("history.jsonl");
+    match elo_system.unsaved_history() {
+        Some(records) => append_history(&history_path, records)?,
+        None => save_history(&history_path, elo_system.history())?,
+    }
+    elo_system.mark_history_saved();
+    elo_system.settings().save(&project_dir.join("settings.json"))?;
+
+    let elo_path = project_dir.join("elo_data.json");
+    let serialized = serde_json::to_string_pretty(elo_system)?;
+    write_atomic(&elo_path, serialized.as_bytes())
+        .with_context(|| format!("Failed to create Elo data file at '{:?}'", elo_path))
+}
+
+/// Replaces a file by writing a temporary file next to it and renaming it into place, so
+/// that an interrupted write leaves the previous version intact.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
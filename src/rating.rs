@@ -0,0 +1,470 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use skillratings::{
+    elo::{self, elo, elo_rating_period, EloConfig, EloRating},
+    glicko::{self, glicko, glicko_rating_period, GlickoConfig, GlickoRating},
+    glicko2::{self, glicko2, glicko2_rating_period, Glicko2Config, Glicko2Rating},
+    trueskill::{
+        trueskill, trueskill_multi_team, trueskill_rating_period, trueskill_two_teams, TrueSkillConfig,
+        TrueSkillRating,
+    },
+    weng_lin::{
+        self, weng_lin, weng_lin_multi_team, weng_lin_rating_period, weng_lin_two_teams, WengLinConfig,
+        WengLinRating,
+    },
+    MultiTeamOutcome, Outcomes,
+};
+use std::{cmp::Ordering, f64::consts::SQRT_2, fmt};
+
+use crate::settings::Settings;
+
+/// The rating algorithm used by a project.
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RatingAlgorithm {
+    /// Classic Elo with a fixed K-factor
+    Elo,
+    /// Glicko, which adds a rating deviation
+    Glicko,
+    /// Glicko-2, which adds a rating volatility
+    #[default]
+    Glicko2,
+    /// Microsoft's TrueSkill
+    #[value(name = "trueskill")]
+    #[serde(rename = "trueskill")]
+    TrueSkill,
+    /// Weng-Lin (OpenSkill)
+    WengLin,
+}
+
+/// Algorithm-independent rating state of a player.
+///
+/// Algorithms that don't track a deviation or volatility leave those fields at zero.
+/// TrueSkill and Weng-Lin store their uncertainty (sigma) as the deviation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    #[serde(default)]
+    pub deviation: f64,
+    #[serde(default)]
+    pub volatility: f64,
+}
+
+/// Predicted outcome of a game, from player one's perspective.
+#[derive(Debug, Clone, Copy)]
+pub struct Prediction {
+    pub win: f64,
+    pub loss: f64,
+    /// Only TrueSkill models draws explicitly. For the other algorithms, `win` is the
+    /// expected score of player one, which counts a draw as half a win.
+    pub draw: Option<f64>,
+}
+
+impl Rating {
+    /// The 95% confidence interval, rating ± 1.96·RD.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        (self.rating - 1.96 * self.deviation, self.rating + 1.96 * self.deviation)
+    }
+
+    /// A conservative skill estimate, rating − 2·RD, that penalises uncertain ratings.
+    pub fn conservative(&self) -> f64 {
+        self.rating - 2.0 * self.deviation
+    }
+
+    /// Applies `weight` times the change from `self` to `updated`.
+    ///
+    /// The deviation and volatility change multiplicatively so they stay positive.
+    pub fn scale_change(self, updated: Rating, weight: f64) -> Rating {
+        Rating {
+            rating: self.rating + weight * (updated.rating - self.rating),
+            deviation: self.deviation * (weight * log_ratio(updated.deviation, self.deviation)).exp(),
+            volatility: self.volatility * (weight * log_ratio(updated.volatility, self.volatility)).exp(),
+        }
+    }
+
+    /// The rating after `days` without a game. As in Glicko, the deviation grows to
+    /// √(RD² + c²·t) with `c` the growth per day, but never beyond `max`.
+    pub fn decayed(self, days: f64, c: f64, max: f64) -> Rating {
+        if days <= 0.0 || c <= 0.0 || self.deviation >= max {
+            return self;
+        }
+        Rating {
+            deviation: (self.deviation.powi(2) + c * c * days).sqrt().min(max),
+            ..self
+        }
+    }
+}
+
+impl RatingAlgorithm {
+    /// Whether the algorithm tracks a rating deviation (or uncertainty).
+    pub fn has_deviation(self) -> bool {
+        self != RatingAlgorithm::Elo
+    }
+
+    /// Whether the algorithm tracks a rating volatility.
+    pub fn has_volatility(self) -> bool {
+        self == RatingAlgorithm::Glicko2
+    }
+
+    /// The algorithm's own default starting rating.
+    pub fn default_rating(self) -> Rating {
+        match self {
+            RatingAlgorithm::Elo => EloRating::new().into(),
+            RatingAlgorithm::Glicko => GlickoRating::new().into(),
+            RatingAlgorithm::Glicko2 => Glicko2Rating::new().into(),
+            RatingAlgorithm::TrueSkill => TrueSkillRating::new().into(),
+            RatingAlgorithm::WengLin => WengLinRating::new().into(),
+        }
+    }
+
+    /// The rating a newly added player starts with under the given settings.
+    ///
+    /// Fields the algorithm doesn't track are dropped.
+    pub fn initial_rating(self, settings: &Settings) -> Rating {
+        let rating = Rating {
+            rating: settings.default_rating,
+            deviation: settings.default_deviation,
+            volatility: settings.default_volatility,
+        };
+        match self {
+            RatingAlgorithm::Elo => EloRating::from(rating).into(),
+            RatingAlgorithm::Glicko => GlickoRating::from(rating).into(),
+            RatingAlgorithm::Glicko2 => rating,
+            RatingAlgorithm::TrueSkill => TrueSkillRating::from(rating).into(),
+            RatingAlgorithm::WengLin => WengLinRating::from(rating).into(),
+        }
+    }
+
+    /// Rates a single game, returning the new ratings of both players.
+    pub fn rate(
+        self,
+        player1: &Rating,
+        player2: &Rating,
+        outcome: &Outcomes,
+        settings: &Settings,
+    ) -> (Rating, Rating) {
+        match self {
+            RatingAlgorithm::Elo => {
+                let config = elo_config(settings);
+                let (a, b) = elo(&(*player1).into(), &(*player2).into(), outcome, &config);
+                (a.into(), b.into())
+            }
+            RatingAlgorithm::Glicko => {
+                let config = GlickoConfig::new();
+                let (a, b) = glicko(&(*player1).into(), &(*player2).into(), outcome, &config);
+                (a.into(), b.into())
+            }
+            RatingAlgorithm::Glicko2 => {
+                let config = glicko2_config(settings);
+                let (a, b) = glicko2(&(*player1).into(), &(*player2).into(), outcome, &config);
+                (a.into(), b.into())
+            }
+            RatingAlgorithm::TrueSkill => {
+                let config = trueskill_config(settings);
+                let (a, b) = trueskill(&(*player1).into(), &(*player2).into(), outcome, &config);
+                (a.into(), b.into())
+            }
+            RatingAlgorithm::WengLin => {
+                let config = weng_lin_config(settings);
+                let (a, b) = weng_lin(&(*player1).into(), &(*player2).into(), outcome, &config);
+                (a.into(), b.into())
+            }
+        }
+    }
+
+    /// Rates all of a player's games in one rating period, against the opponents'
+    /// ratings at the start of the period, returning the player's new rating.
+    ///
+    /// For Glicko and Glicko-2 this is how the algorithms are meant to be used; the
+    /// deviation shrinks once for the whole period instead of after every game.
+    pub fn rate_period(self, player: &Rating, results: &[(Rating, Outcomes)], settings: &Settings) -> Rating {
+        fn convert<T: From<Rating>>(results: &[(Rating, Outcomes)]) -> Vec<(T, Outcomes)> {
+            results.iter().map(|(opponent, outcome)| ((*opponent).into(), *outcome)).collect()
+        }
+        match self {
+            RatingAlgorithm::Elo => {
+                elo_rating_period(&(*player).into(), &convert(results), &elo_config(settings)).into()
+            }
+            RatingAlgorithm::Glicko => {
+                glicko_rating_period(&(*player).into(), &convert(results), &GlickoConfig::new()).into()
+            }
+            RatingAlgorithm::Glicko2 => {
+                glicko2_rating_period(&(*player).into(), &convert(results), &glicko2_config(settings)).into()
+            }
+            RatingAlgorithm::TrueSkill => {
+                trueskill_rating_period(&(*player).into(), &convert(results), &trueskill_config(settings)).into()
+            }
+            RatingAlgorithm::WengLin => {
+                weng_lin_rating_period(&(*player).into(), &convert(results), &weng_lin_config(settings)).into()
+            }
+        }
+    }
+
+    /// Rates a game between two teams, returning the new ratings of every member.
+    ///
+    /// TrueSkill and Weng-Lin rate teams natively. The other algorithms rate each member
+    /// as if they had played a single opponent with the opposing team's average rating.
+    pub fn rate_teams(
+        self,
+        team1: &[Rating],
+        team2: &[Rating],
+        outcome: &Outcomes,
+        settings: &Settings,
+    ) -> (Vec<Rating>, Vec<Rating>) {
+        match self {
+            RatingAlgorithm::TrueSkill => {
+                let team1: Vec<TrueSkillRating> = team1.iter().map(|r| (*r).into()).collect();
+                let team2: Vec<TrueSkillRating> = team2.iter().map(|r| (*r).into()).collect();
+                let (a, b) = trueskill_two_teams(&team1, &team2, outcome, &trueskill_config(settings));
+                (a.into_iter().map(Rating::from).collect(), b.into_iter().map(Rating::from).collect())
+            }
+            RatingAlgorithm::WengLin => {
+                let team1: Vec<WengLinRating> = team1.iter().map(|r| (*r).into()).collect();
+                let team2: Vec<WengLinRating> = team2.iter().map(|r| (*r).into()).collect();
+                let (a, b) = weng_lin_two_teams(&team1, &team2, outcome, &weng_lin_config(settings));
+                (a.into_iter().map(Rating::from).collect(), b.into_iter().map(Rating::from).collect())
+            }
+            RatingAlgorithm::Elo | RatingAlgorithm::Glicko | RatingAlgorithm::Glicko2 => {
+                let (average1, average2) = (team_average(team1), team_average(team2));
+                let a = team1.iter().map(|r| self.rate(r, &average2, outcome, settings).0).collect();
+                let b = team2.iter().map(|r| self.rate(&average1, r, outcome, settings).1).collect();
+                (a, b)
+            }
+        }
+    }
+
+    /// Rates a free-for-all between players finishing at the given places (1 is best,
+    /// tied players share a place), returning everyone's new rating.
+    ///
+    /// TrueSkill and Weng-Lin rate all players jointly with their multi-team models.
+    /// The other algorithms rate a game against every opponent from the ratings before
+    /// the event and apply the average change, scaled by the `multiplayer_weight` setting.
+    pub fn rate_ranking(self, ratings: &[Rating], places: &[usize], settings: &Settings) -> Vec<Rating> {
+        match self {
+            RatingAlgorithm::TrueSkill => {
+                let teams: Vec<[TrueSkillRating; 1]> = ratings.iter().map(|r| [(*r).into()]).collect();
+                let teams_and_ranks: Vec<_> = teams
+                    .iter()
+                    .zip(places)
+                    .map(|(team, &place)| (&team[..], MultiTeamOutcome::new(place)))
+                    .collect();
+                trueskill_multi_team(&teams_and_ranks, &trueskill_config(settings))
+                    .into_iter()
+                    .map(|team| team[0].into())
+                    .collect()
+            }
+            RatingAlgorithm::WengLin => {
+                let teams: Vec<[WengLinRating; 1]> = ratings.iter().map(|r| [(*r).into()]).collect();
+                let teams_and_ranks: Vec<_> = teams
+                    .iter()
+                    .zip(places)
+                    .map(|(team, &place)| (&team[..], MultiTeamOutcome::new(place)))
+                    .collect();
+                weng_lin_multi_team(&teams_and_ranks, &weng_lin_config(settings))
+                    .into_iter()
+                    .map(|team| team[0].into())
+                    .collect()
+            }
+            RatingAlgorithm::Elo | RatingAlgorithm::Glicko | RatingAlgorithm::Glicko2 => {
+                let scale = settings.multiplayer_weight / (ratings.len() - 1) as f64;
+                (0..ratings.len())
+                    .map(|i| {
+                        let old = ratings[i];
+                        // Deviations and volatilities are combined multiplicatively so they stay positive.
+                        let (mut rating, mut deviation, mut volatility) = (0.0, 0.0, 0.0);
+                        for j in (0..ratings.len()).filter(|&j| j != i) {
+                            let outcome = match places[i].cmp(&places[j]) {
+                                Ordering::Less => Outcomes::WIN,
+                                Ordering::Greater => Outcomes::LOSS,
+                                Ordering::Equal => Outcomes::DRAW,
+                            };
+                            let (new, _) = self.rate(&old, &ratings[j], &outcome, settings);
+                            rating += new.rating - old.rating;
+                            deviation += log_ratio(new.deviation, old.deviation);
+                            volatility += log_ratio(new.volatility, old.volatility);
+                        }
+                        Rating {
+                            rating: old.rating + scale * rating,
+                            deviation: old.deviation * (scale * deviation).exp(),
+                            volatility: old.volatility * (scale * volatility).exp(),
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Predicts the outcome of a game between two players.
+    pub fn predict(self, player1: &Rating, player2: &Rating, settings: &Settings) -> Prediction {
+        let (win, loss) = match self {
+            RatingAlgorithm::Elo => elo::expected_score(&(*player1).into(), &(*player2).into()),
+            RatingAlgorithm::Glicko => glicko::expected_score(&(*player1).into(), &(*player2).into()),
+            RatingAlgorithm::Glicko2 => glicko2::expected_score(&(*player1).into(), &(*player2).into()),
+            RatingAlgorithm::TrueSkill => {
+                // Performance difference ~ N(Δμ, c²); a draw happens within the draw margin.
+                let c = (2.0 * settings.beta.powi(2) + player1.deviation.powi(2) + player2.deviation.powi(2)).sqrt();
+                let margin = inverse_normal_cdf((settings.draw_probability + 1.0) / 2.0) * SQRT_2 * settings.beta;
+                let delta = player1.rating - player2.rating;
+                let win = normal_cdf((delta - margin) / c);
+                let loss = normal_cdf((-delta - margin) / c);
+                return Prediction { win, loss, draw: Some(1.0 - win - loss) };
+            }
+            RatingAlgorithm::WengLin => {
+                weng_lin::expected_score(&(*player1).into(), &(*player2).into(), &weng_lin_config(settings))
+            }
+        };
+        Prediction { win, loss, draw: None }
+    }
+}
+
+fn elo_config(settings: &Settings) -> EloConfig {
+    EloConfig { k: settings.k_factor }
+}
+
+fn glicko2_config(settings: &Settings) -> Glicko2Config {
+    Glicko2Config {
+        tau: settings.tau,
+        convergence_tolerance: settings.convergence_tolerance,
+    }
+}
+
+fn trueskill_config(settings: &Settings) -> TrueSkillConfig {
+    TrueSkillConfig {
+        draw_probability: settings.draw_probability,
+        beta: settings.beta,
+        ..TrueSkillConfig::new()
+    }
+}
+
+fn weng_lin_config(settings: &Settings) -> WengLinConfig {
+    WengLinConfig {
+        beta: settings.beta,
+        ..WengLinConfig::new()
+    }
+}
+
+/// A single opponent standing in for a whole team: the members' average rating and
+/// volatility, with the root mean square of their deviations.
+fn team_average(team: &[Rating]) -> Rating {
+    let n = team.len() as f64;
+    Rating {
+        rating: team.iter().map(|r| r.rating).sum::<f64>() / n,
+        deviation: (team.iter().map(|r| r.deviation.powi(2)).sum::<f64>() / n).sqrt(),
+        volatility: team.iter().map(|r| r.volatility).sum::<f64>() / n,
+    }
+}
+
+/// `ln(new / old)`, or zero for fields the algorithm doesn't track.
+fn log_ratio(new: f64, old: f64) -> f64 {
+    if old > 0.0 && new > 0.0 {
+        (new / old).ln()
+    } else {
+        0.0
+    }
+}
+
+/// Standard normal cumulative distribution function.
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / SQRT_2)
+}
+
+/// Inverse of `normal_cdf`, found by bisection.
+fn inverse_normal_cdf(p: f64) -> f64 {
+    let (mut low, mut high) = (-10.0, 10.0);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if normal_cdf(mid) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Complementary error function (Numerical Recipes' `erfcc`, relative error below 1.2e-7).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + z / 2.0);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let r = t * poly.exp();
+    if x < 0.0 {
+        2.0 - r
+    } else {
+        r
+    }
+}
+
+impl fmt::Display for RatingAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no skipped variants");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+impl From<EloRating> for Rating {
+    fn from(r: EloRating) -> Self {
+        Self { rating: r.rating, deviation: 0.0, volatility: 0.0 }
+    }
+}
+
+impl From<Rating> for EloRating {
+    fn from(r: Rating) -> Self {
+        Self { rating: r.rating }
+    }
+}
+
+impl From<GlickoRating> for Rating {
+    fn from(r: GlickoRating) -> Self {
+        Self { rating: r.rating, deviation: r.deviation, volatility: 0.0 }
+    }
+}
+
+impl From<Rating> for GlickoRating {
+    fn from(r: Rating) -> Self {
+        Self { rating: r.rating, deviation: r.deviation }
+    }
+}
+
+impl From<Glicko2Rating> for Rating {
+    fn from(r: Glicko2Rating) -> Self {
+        Self { rating: r.rating, deviation: r.deviation, volatility: r.volatility }
+    }
+}
+
+impl From<Rating> for Glicko2Rating {
+    fn from(r: Rating) -> Self {
+        Self { rating: r.rating, deviation: r.deviation, volatility: r.volatility }
+    }
+}
+
+impl From<TrueSkillRating> for Rating {
+    fn from(r: TrueSkillRating) -> Self {
+        Self { rating: r.rating, deviation: r.uncertainty, volatility: 0.0 }
+    }
+}
+
+impl From<Rating> for TrueSkillRating {
+    fn from(r: Rating) -> Self {
+        Self { rating: r.rating, uncertainty: r.deviation }
+    }
+}
+
+impl From<WengLinRating> for Rating {
+    fn from(r: WengLinRating) -> Self {
+        Self { rating: r.rating, deviation: r.uncertainty, volatility: 0.0 }
+    }
+}
+
+impl From<Rating> for WengLinRating {
+    fn from(r: Rating) -> Self {
+        Self { rating: r.rating, uncertainty: r.deviation }
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::error::BeloError;
+use crate::project::write_atomic;
+use crate::rating::RatingAlgorithm;
+
+/// Names of all tunable settings, in display order.
+pub const SETTING_KEYS: [&str; 12] = [
+    "default_rating",
+    "default_deviation",
+    "default_volatility",
+    "tau",
+    "convergence_tolerance",
+    "k_factor",
+    "beta",
+    "draw_probability",
+    "multiplayer_weight",
+    "margin_weight",
+    "rating_period_days",
+    "deviation_decay",
+];
+
+/// Per-project rating parameters, stored in `settings.json` next to `elo_data.json`.
+///
+/// Every algorithm reads only the parameters that apply to it:
+/// `k_factor` for Elo, `tau` and `convergence_tolerance` for Glicko-2,
+/// `beta` for TrueSkill and Weng-Lin, and `draw_probability` for TrueSkill.
+/// `multiplayer_weight` applies to free-for-alls rated by Elo, Glicko and Glicko-2,
+/// `margin_weight` to graded and scored results under every algorithm,
+/// `rating_period_days` to projects using rating periods, and `deviation_decay` to every
+/// algorithm with a rating deviation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    /// Rating a newly added player starts with.
+    pub default_rating: f64,
+    /// Rating deviation (or uncertainty) a newly added player starts with.
+    pub default_deviation: f64,
+    /// Glicko-2 volatility a newly added player starts with.
+    pub default_volatility: f64,
+    /// Glicko-2 system constant constraining the change in volatility.
+    pub tau: f64,
+    /// Glicko-2 convergence tolerance used when computing the new volatility.
+    pub convergence_tolerance: f64,
+    /// Elo K-factor.
+    pub k_factor: f64,
+    /// TrueSkill / Weng-Lin skill class width.
+    pub beta: f64,
+    /// TrueSkill probability of a draw between two equal players.
+    pub draw_probability: f64,
+    /// How much a free-for-all moves Elo and Glicko ratings, relative to a single game.
+    /// Each player gets the average of the changes from a game against every opponent,
+    /// times this weight, so `n − 1` counts every opponent as a full game.
+    #[serde(default = "default_multiplayer_weight")]
+    pub multiplayer_weight: f64,
+    /// How much the margin of a result scales its rating change: `gt:strong` counts as
+    /// `1 + w` games, `gt:weak` as `1 − w` and a score won by `d` points as `1 + w·ln(d)`.
+    #[serde(default = "default_margin_weight")]
+    pub margin_weight: f64,
+    /// Length in days after which an open rating period closes and the next one starts,
+    /// going by the time games are recorded. 0 closes periods only with `belo period close`.
+    #[serde(default)]
+    pub rating_period_days: f64,
+    /// Glicko's `c`: after `t` days without a game a player's deviation grows to
+    /// √(RD² + c²·t), up to `default_deviation`. 0 keeps deviations as they are.
+    /// Rating periods already widen the deviation of players who sit them out.
+    #[serde(default)]
+    pub deviation_decay: f64,
+    /// Every change made with `belo config set` or by switching algorithms, oldest first.
+    #[serde(default)]
+    pub changes: Vec<SettingChange>,
+}
+
+/// A single recorded change to a project setting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingChange {
+    pub key: String,
+    pub old: f64,
+    pub new: f64,
+    pub changed_at: DateTime<Utc>,
+    /// The algorithm the project switched to, for defaults reset by the switch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<RatingAlgorithm>,
+}
+
+impl Settings {
+    /// The default settings for the given algorithm.
+    pub fn new(algorithm: RatingAlgorithm) -> Self {
+        let initial = algorithm.default_rating();
+        Self {
+            default_rating: initial.rating,
+            default_deviation: initial.deviation,
+            default_volatility: 0.06,
+            tau: 0.5,
+            convergence_tolerance: 0.000_001,
+            k_factor: 32.0,
+            beta: 25.0 / 6.0,
+            draw_probability: 0.1,
+            multiplayer_weight: default_multiplayer_weight(),
+            margin_weight: default_margin_weight(),
+            rating_period_days: 0.0,
+            deviation_decay: 0.0,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Loads the settings at `path`, falling back to the algorithm's defaults
+    /// for projects created before settings existed.
+    pub fn load(path: &Path, algorithm: RatingAlgorithm) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new(algorithm));
+        }
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to open settings file at '{:?}'", path))?;
+        let settings: Settings = serde_json::from_str(&contents)
+            .with_context(|| "Failed to parse settings file")?;
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let serialized = serde_json::to_string_pretty(self)?;
+        write_atomic(path, serialized.as_bytes())
+            .with_context(|| format!("Failed to create settings file at '{:?}'", path))
+    }
+
+    /// Returns the value of the setting named `key`.
+    pub fn get(&self, key: &str) -> Result<f64> {
+        match key {
+            "default_rating" => Ok(self.default_rating),
+            "default_deviation" => Ok(self.default_deviation),
+            "default_volatility" => Ok(self.default_volatility),
+            "tau" => Ok(self.tau),
+            "convergence_tolerance" => Ok(self.convergence_tolerance),
+            "k_factor" => Ok(self.k_factor),
+            "beta" => Ok(self.beta),
+            "draw_probability" => Ok(self.draw_probability),
+            "multiplayer_weight" => Ok(self.multiplayer_weight),
+            "margin_weight" => Ok(self.margin_weight),
+            "rating_period_days" => Ok(self.rating_period_days),
+            "deviation_decay" => Ok(self.deviation_decay),
+            _ => Err(BeloError::UnknownSetting(key.to_string()).into()),
+        }
+    }
+
+    /// Validates and changes the setting named `key`, recording the change.
+    pub fn set(&mut self, key: &str, value: f64) -> Result<()> {
+        let old = self.get(key)?;
+        validate(key, value)?;
+
+        let field = match key {
+            "default_rating" => &mut self.default_rating,
+            "default_deviation" => &mut self.default_deviation,
+            "default_volatility" => &mut self.default_volatility,
+            "tau" => &mut self.tau,
+            "convergence_tolerance" => &mut self.convergence_tolerance,
+            "k_factor" => &mut self.k_factor,
+            "beta" => &mut self.beta,
+            "draw_probability" => &mut self.draw_probability,
+            "multiplayer_weight" => &mut self.multiplayer_weight,
+            "margin_weight" => &mut self.margin_weight,
+            "rating_period_days" => &mut self.rating_period_days,
+            "deviation_decay" => &mut self.deviation_decay,
+            _ => unreachable!("key was checked by get"),
+        };
+        *field = value;
+
+        self.changes.push(SettingChange {
+            key: key.to_string(),
+            old,
+            new: value,
+            changed_at: Utc::now(),
+            algorithm: None,
+        });
+        Ok(())
+    }
+
+    /// Resets the settings that depend on the rating scale to the defaults of `algorithm`,
+    /// keeping the rest.
+    ///
+    /// Both resets are recorded even when a value stays the same, so that the record of
+    /// changes shows every switch.
+    pub fn switch_algorithm(&mut self, algorithm: RatingAlgorithm) {
+        let initial = algorithm.default_rating();
+        let changed_at = Utc::now();
+        for (key, field, new) in [
+            ("default_rating", &mut self.default_rating, initial.rating),
+            ("default_deviation", &mut self.default_deviation, initial.deviation),
+        ] {
+            self.changes.push(SettingChange {
+                key: key.to_string(),
+                old: *field,
+                new,
+                changed_at,
+                algorithm: Some(algorithm),
+            });
+            *field = new;
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new(RatingAlgorithm::default())
+    }
+}
+
+fn default_multiplayer_weight() -> f64 {
+    1.0
+}
+
+fn default_margin_weight() -> f64 {
+    0.5
+}
+
+fn validate(key: &str, value: f64) -> Result<()> {
+    let reason = if !value.is_finite() {
+        "must be a finite number"
+    } else {
+        match key {
+            "default_rating" => return Ok(()),
+            "default_deviation" | "rating_period_days" | "deviation_decay" if value < 0.0 => "cannot be negative",
+            "draw_probability" | "margin_weight" if !(0.0..1.0).contains(&value) => "must be in the range [0, 1)",
+            "default_volatility" | "tau" | "convergence_tolerance" | "k_factor" | "beta"
+            | "multiplayer_weight" if value <= 0.0 => "must be positive",
+            _ => return Ok(()),
+        }
+    };
+    Err(BeloError::InvalidSetting { key: key.to_string(), reason }.into())
+}
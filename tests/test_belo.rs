@@ -1,30 +1,62 @@
 use assert_cmd::Command;
 use indicatif::{ProgressBar, ProgressStyle};
 use rand::{seq::SliceRandom, thread_rng, Rng};
+use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// A project living in its own temporary home directory, so that tests
+/// running in parallel don't activate each other's projects.
 struct TestEnv {
     name: String,
+    home: PathBuf,
 }
 
 impl TestEnv {
     fn new(test_name: &str) -> Self {
+        Self::with_args(test_name, &[])
+    }
+    fn with_args(test_name: &str, init_args: &[&str]) -> Self {
+        let env = Self::empty(test_name);
+        env.cmd().arg("init").arg(&env.name).args(init_args).assert().success();
+        env
+    }
+    /// A temporary home directory without any project yet.
+    fn empty(test_name: &str) -> Self {
         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
         let env_name = format!("{}_{}", test_name, now);
-        let mut cmd_init = Command::cargo_bin("belo").unwrap();
-        cmd_init.arg("init").arg(&env_name).assert().success();
-        Self { name: env_name }
+        let home = std::env::temp_dir().join("belo_tests").join(&env_name);
+        std::fs::create_dir_all(&home).unwrap();
+        Self { name: env_name, home }
+    }
+    fn cmd(&self) -> Command {
+        let mut cmd = Command::cargo_bin("belo").unwrap();
+        cmd.env("HOME", &self.home);
+        cmd
     }
     fn activate(&self) {
-        let mut cmd_activate = Command::cargo_bin("belo").unwrap();
-        cmd_activate.arg("activate").arg(&self.name).assert().success();
+        self.cmd().arg("activate").arg(&self.name).assert().success();
+    }
+    fn elo_path(&self) -> PathBuf {
+        self.home.join(".cache").join("belo").join(&self.name).join("elo_data.json")
+    }
+    fn elo_data(&self) -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(self.elo_path()).unwrap()).unwrap()
+    }
+    /// Every player's stats and rating, without the time they last played,
+    /// so that players of separate projects can be compared.
+    fn players(&self) -> serde_json::Value {
+        let mut players = self.elo_data()["players"].clone();
+        for player in players.as_object_mut().unwrap().values_mut() {
+            player.as_object_mut().unwrap().remove("last_played");
+        }
+        players
     }
 }
 
 impl Drop for TestEnv {
     fn drop(&mut self) {
-        let mut cmd_delete = Command::cargo_bin("belo").unwrap();
-        let _ = cmd_delete.arg("delete").arg(&self.name).assert();
+        let _ = self.cmd().arg("delete").arg(&self.name).assert();
+        let _ = std::fs::remove_dir_all(&self.home);
     }
 }
 
@@ -34,15 +66,15 @@ fn test_commands() {
     env.activate();
 
     // 3. Add two players
-    let mut cmd_add_player1 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_add_player1 = env.cmd();
     cmd_add_player1.arg("add").arg("test_player1");
     cmd_add_player1.assert().success();
-    let mut cmd_add_player2 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_add_player2 = env.cmd();
     cmd_add_player2.arg("add").arg("test_player2");
     cmd_add_player2.assert().success();
 
     // 4. Record a match: test_player1 > test_player2
-    let mut cmd_vs = Command::cargo_bin("belo").unwrap();
+    let mut cmd_vs = env.cmd();
     cmd_vs.arg("vs").arg("test_player1").arg("gt").arg("test_player2");
     cmd_vs.assert().success();
 }
@@ -51,19 +83,21 @@ fn test_commands() {
 fn test_list_projects_command() {
     let env = TestEnv::new("test_list_projects_command");
     env.activate();
-    let mut cmd_list = Command::cargo_bin("belo").unwrap();
+    let mut cmd_list = env.cmd();
     cmd_list.arg("list").assert().success();
 }
 
 #[test]
 fn test_whoami_with_no_active_project() {
-    let mut cmd_whoami = Command::cargo_bin("belo").unwrap();
+    let env = TestEnv::empty("test_whoami_with_no_active_project");
+    let mut cmd_whoami = env.cmd();
     cmd_whoami.arg("whoami").assert().success();
 }
 
 #[test]
 fn test_activate_nonexistent_project() {
-    let mut cmd_activate = Command::cargo_bin("belo").unwrap();
+    let env = TestEnv::empty("test_activate_nonexistent_project");
+    let mut cmd_activate = env.cmd();
     cmd_activate.arg("activate").arg("nonexistent").assert().failure();
 }
 
@@ -73,31 +107,31 @@ fn test_unexpected_index() {
     env.activate();
 
     // Add four users
-    let mut cmd_add_player1 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_add_player1 = env.cmd();
     cmd_add_player1.arg("add").arg("test_player1");
     cmd_add_player1.assert().success();
-    let mut cmd_add_player2 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_add_player2 = env.cmd();
     cmd_add_player2.arg("add").arg("test_player2");
     cmd_add_player2.assert().success();
-    let mut cmd_add_player3 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_add_player3 = env.cmd();
     cmd_add_player3.arg("add").arg("test_player3");
     cmd_add_player3.assert().success();
-    let mut cmd_add_player4 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_add_player4 = env.cmd();
     cmd_add_player4.arg("add").arg("test_player4");
     cmd_add_player4.assert().success();
 
     // Record a match that throws an error (version 0.1.0)
-    let mut cmd_vs1 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_vs1 = env.cmd();
     cmd_vs1.arg("vs").arg("test_player1").arg("gt").arg("test_player2");
     cmd_vs1.assert().success();
-    let mut cmd_vs2 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_vs2 = env.cmd();
     cmd_vs2.arg("vs").arg("test_player2").arg("gt").arg("test_player3");
     cmd_vs2.assert().success();
-    let mut cmd_vs3 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_vs3 = env.cmd();
     cmd_vs3.arg("vs").arg("test_player3").arg("gt").arg("test_player4");
     cmd_vs3.assert().success();
     // This command failed before
-    let mut cmd_vs4 = Command::cargo_bin("belo").unwrap();
+    let mut cmd_vs4 = env.cmd();
     cmd_vs4.arg("vs").arg("test_player4").arg("gt").arg("test_player1");
     cmd_vs4.assert().success();
 }
@@ -116,7 +150,7 @@ fn test_big_load() {
     bar.set_style(style.clone());
 
     for i in 0..500 {
-        let mut cmd_add_player = Command::cargo_bin("belo").unwrap();
+        let mut cmd_add_player = env.cmd();
         cmd_add_player.arg("add").arg(format!("test_player{}", i));
         cmd_add_player.assert().success();
         bar.inc(1); // Increment progress bar
@@ -131,10 +165,11 @@ fn test_big_load() {
     let outcomes = ["gt", "lt", "eq"];
 
     for _ in 0..100 {
-        let i = rng.gen_range(1..=500);
-        let j = rng.gen_range(1..=500);
+        // Only existing players, never against themselves
+        let i = rng.gen_range(0..500);
+        let j = (i + rng.gen_range(1..500)) % 500;
         let outcome = outcomes.choose(&mut rng).unwrap();
-        let mut cmd_vs = Command::cargo_bin("belo").unwrap();
+        let mut cmd_vs = env.cmd();
         cmd_vs
             .arg("vs")
             .arg(format!("test_player{}", i))
@@ -144,4 +179,861 @@ fn test_big_load() {
         bar.inc(1); // Increment progress bar
     }
     bar.finish_with_message("Matches recorded!");
-}
\ No newline at end of file
+}
+#[test]
+fn test_init_with_algorithm() {
+    let env = TestEnv::with_args("test_init_with_algorithm", &["--algorithm", "trueskill"]);
+    env.activate();
+    assert_eq!(env.elo_data()["algorithm"], "trueskill");
+
+    let mut cmd_add_player1 = env.cmd();
+    cmd_add_player1.arg("add").arg("test_player1").assert().success();
+    let mut cmd_add_player2 = env.cmd();
+    cmd_add_player2.arg("add").arg("test_player2").assert().success();
+    let mut cmd_vs = env.cmd();
+    cmd_vs.arg("vs").arg("test_player1").arg("gt").arg("test_player2").assert().success();
+
+    // TrueSkill ratings start at 25 instead of Glicko-2's 1500
+    let data = env.elo_data();
+    let rating = data["players"]["test_player1"]["rating"]["rating"].as_f64().unwrap();
+    assert!(rating > 25.0 && rating < 50.0);
+}
+
+#[test]
+fn test_init_with_unknown_algorithm() {
+    let env = TestEnv::empty("test_init_with_unknown_algorithm");
+    let mut cmd_init = env.cmd();
+    cmd_init.arg("init").arg(&env.name).arg("--algorithm").arg("chess");
+    cmd_init.assert().failure();
+}
+
+#[test]
+fn test_config_get_set() {
+    let env = TestEnv::new("test_config_get_set");
+    env.activate();
+
+    let output = env.cmd().arg("config").arg("get").arg("tau").output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "0.5");
+
+    env.cmd().arg("config").arg("set").arg("default_rating").arg("1200").assert().success();
+    env.cmd().arg("config").arg("set").arg("tau").arg("-1").assert().failure();
+    env.cmd().arg("config").arg("set").arg("no_such_key").arg("1").assert().failure();
+
+    // New players start from the changed default rating
+    env.cmd().arg("add").arg("test_player1").assert().success();
+    let rating = env.elo_data()["players"]["test_player1"]["rating"]["rating"].as_f64().unwrap();
+    assert_eq!(rating, 1200.0);
+
+    // Only the valid change is recorded
+    let path = env.home.join(".cache").join("belo").join(&env.name).join("settings.json");
+    let settings: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(settings["changes"].as_array().unwrap().len(), 1);
+    assert_eq!(settings["changes"][0]["key"], "default_rating");
+}
+
+#[test]
+fn test_history_and_recompute() {
+    let env = TestEnv::new("test_history_and_recompute");
+    env.activate();
+
+    env.cmd().arg("add").arg("test_player1").assert().success();
+    env.cmd().arg("add").arg("test_player2").assert().success();
+    env.cmd().arg("vs").arg("test_player1").arg("gt").arg("test_player2").assert().success();
+    env.cmd().arg("vs").arg("test_player1").arg("eq").arg("test_player2").assert().success();
+
+    let history_path = env.home.join(".cache").join("belo").join(&env.name).join("history.jsonl");
+    let history = std::fs::read_to_string(&history_path).unwrap();
+    assert_eq!(history.lines().count(), 2);
+
+    // Undoing rewrites the log, while new matches are appended to it
+    env.cmd().arg("undo").assert().success();
+    env.cmd().arg("vs").arg("test_player1").arg("eq").arg("test_player2").assert().success();
+    let history = std::fs::read_to_string(&history_path).unwrap();
+    let ids: Vec<u64> = history
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ids, [1, 2]);
+    assert!(!history_path.with_file_name("history.jsonl.tmp").exists());
+
+    // Replaying the log with unchanged settings reproduces the same ratings
+    let before = env.elo_data();
+    env.cmd().arg("recompute").assert().success();
+    assert_eq!(env.elo_data(), before);
+
+    // Switching algorithms rebuilds the ratings on the new scale
+    env.cmd().args(["config", "set", "k_factor", "16"]).assert().success();
+    env.cmd().arg("recompute").arg("--algorithm").arg("elo").assert().success();
+    let data = env.elo_data();
+    assert_eq!(data["algorithm"], "elo");
+    assert!(data["players"]["test_player1"]["rating"]["rating"].as_f64().unwrap() > 1000.0);
+    assert_eq!(data["players"]["test_player1"]["wins"], 1);
+    assert_eq!(data["players"]["test_player1"]["ties"], 1);
+
+    // ... keeping settings that don't depend on the scale and logging the reset defaults
+    let settings_path = env.elo_path().with_file_name("settings.json");
+    let settings: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(settings_path).unwrap()).unwrap();
+    assert_eq!(settings["k_factor"], 16.0);
+    assert_eq!(settings["default_rating"], 1000.0);
+    let changes = settings["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[1]["key"], "default_rating");
+    assert_eq!(changes[1]["old"], 1500.0);
+    assert_eq!(changes[1]["algorithm"], "elo");
+    assert_eq!(changes[2]["key"], "default_deviation");
+}
+
+#[test]
+fn test_edit_and_undo_replay() {
+    let mistaken = TestEnv::new("test_edit_replay_mistaken");
+    let correct = TestEnv::new("test_edit_replay_correct");
+    for env in [&mistaken, &correct] {
+        env.activate();
+        for id in ["test_player1", "test_player2", "test_player3"] {
+            env.cmd().arg("add").arg(id).assert().success();
+        }
+    }
+
+    mistaken.cmd().arg("vs").arg("test_player1").arg("gt").arg("test_player2").assert().success();
+    mistaken.cmd().arg("vs").arg("test_player2").arg("gt").arg("test_player3").assert().success();
+    mistaken.cmd().arg("match").arg("edit").arg("1").arg("--result").arg("lt").assert().success();
+
+    correct.cmd().arg("vs").arg("test_player1").arg("lt").arg("test_player2").assert().success();
+    correct.cmd().arg("vs").arg("test_player2").arg("gt").arg("test_player3").assert().success();
+
+    // Correcting a result ends up exactly as if it had been recorded correctly
+    assert_eq!(mistaken.players(), correct.players());
+
+    // Undoing the last match is the same as never having played it
+    let before_second_match = TestEnv::new("test_edit_replay_single");
+    before_second_match.activate();
+    for id in ["test_player1", "test_player2", "test_player3"] {
+        before_second_match.cmd().arg("add").arg(id).assert().success();
+    }
+    before_second_match.cmd().arg("vs").arg("test_player1").arg("lt").arg("test_player2").assert().success();
+    correct.cmd().arg("undo").assert().success();
+    assert_eq!(correct.players(), before_second_match.players());
+
+    correct.cmd().arg("undo").arg("5").assert().failure();
+    correct.cmd().arg("match").arg("delete").arg("42").assert().failure();
+}
+
+#[test]
+fn test_remove_rename_merge_players() {
+    let env = TestEnv::new("test_remove_rename_merge_players");
+    env.activate();
+    for id in ["test_player1", "test_player2", "test_player2_dup", "test_player3"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+    env.cmd().arg("vs").arg("test_player1").arg("gt").arg("test_player2").assert().success();
+    env.cmd().arg("vs").arg("test_player2_dup").arg("gt").arg("test_player3").assert().success();
+    env.cmd().arg("vs").arg("test_player2").arg("eq").arg("test_player2_dup").assert().success();
+
+    // Merging reassigns the duplicate's games and drops games between the two IDs
+    env.cmd().arg("merge").arg("test_player2_dup").arg("test_player2").assert().success();
+    let data = env.elo_data();
+    assert!(data["players"].get("test_player2_dup").is_none());
+    assert_eq!(data["players"]["test_player2"]["wins"], 1);
+    assert_eq!(data["players"]["test_player2"]["losses"], 1);
+    assert_eq!(data["players"]["test_player2"]["ties"], 0);
+
+    env.cmd().arg("rename").arg("test_player3").arg("test_player1").assert().failure();
+    // Renaming a player to their own name changes nothing
+    env.cmd().arg("rename").arg("test_player3").arg("test_player3").assert().success();
+    assert_eq!(env.elo_data()["players"]["test_player3"]["losses"], 1);
+    env.cmd().arg("rename").arg("test_player3").arg("test_player4").assert().success();
+    assert_eq!(env.elo_data()["players"]["test_player4"]["losses"], 1);
+
+    // Removing a player also removes their games from their opponents' stats
+    env.cmd().arg("remove").arg("test_player1").assert().success();
+    let data = env.elo_data();
+    assert!(data["players"].get("test_player1").is_none());
+    assert_eq!(data["players"]["test_player2"]["losses"], 0);
+    assert_eq!(data["players"]["test_player2"]["wins"], 1);
+    env.cmd().arg("remove").arg("test_player1").assert().failure();
+}
+
+#[test]
+fn test_head_and_info_show_uncertainty() {
+    let env = TestEnv::new("test_head_and_info_show_uncertainty");
+    env.activate();
+    for id in ["test_player1", "test_player2", "test_player3"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+    env.cmd().arg("vs").arg("test_player1").arg("gt").arg("test_player2").assert().success();
+    for _ in 0..3 {
+        env.cmd().arg("vs").arg("test_player2").arg("gt").arg("test_player3").assert().success();
+    }
+
+    let output = env.cmd().arg("info").arg("test_player1").output().unwrap();
+    let info = String::from_utf8_lossy(&output.stdout);
+    assert!(info.contains("RD: 290"));
+    assert!(info.contains("95% CI"));
+    assert!(info.contains("Volatility"));
+
+    // The single lucky win tops the board by rating, but not by the conservative score
+    let first_row = |sort: &str| {
+        let output = env.cmd().arg("head").arg("--sort").arg(sort).output().unwrap();
+        let table = String::from_utf8_lossy(&output.stdout).to_string();
+        table.lines().nth(3).unwrap().to_string()
+    };
+    assert!(first_row("rating").contains("test_player1"));
+    assert!(first_row("conservative").contains("test_player2"));
+}
+
+#[test]
+fn test_predict() {
+    let env = TestEnv::with_args("test_predict", &["--algorithm", "trueskill"]);
+    env.activate();
+    env.cmd().arg("add").arg("test_player1").assert().success();
+    env.cmd().arg("add").arg("test_player2").assert().success();
+    env.cmd().arg("vs").arg("test_player1").arg("gt").arg("test_player2").assert().success();
+
+    let output = env.cmd().arg("predict").arg("test_player1").arg("test_player2").output().unwrap();
+    assert!(output.status.success());
+    let prediction = String::from_utf8_lossy(&output.stdout);
+    let percentages: Vec<f64> = prediction
+        .lines()
+        .filter_map(|line| line.split(": ").nth(1)?.strip_suffix('%')?.parse().ok())
+        .collect();
+
+    // Win, loss and draw probabilities, favouring the winner of the last game
+    assert_eq!(percentages.len(), 3);
+    assert!(percentages[0] > percentages[1]);
+    assert!((percentages.iter().sum::<f64>() - 100.0).abs() < 0.2);
+
+    env.cmd().arg("predict").arg("test_player1").arg("unknown_player").assert().failure();
+}
+
+#[test]
+fn test_bradley_terry_fit_is_order_independent() {
+    let games = [
+        ["test_player1", "gt", "test_player2"],
+        ["test_player2", "gt", "test_player3"],
+        ["test_player1", "eq", "test_player3"],
+        ["test_player3", "gt", "test_player1"],
+    ];
+    let fitted = |env: &TestEnv| {
+        let output = env.cmd().arg("fit").arg("--model").arg("bradley-terry").output().unwrap();
+        assert!(output.status.success());
+        let mut ratings: Vec<(String, String)> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let cells: Vec<&str> = line.split('│').map(str::trim).collect();
+                cells.get(2)?.starts_with("test_player").then(|| (cells[2].to_string(), cells[3].to_string()))
+            })
+            .collect();
+        ratings.sort();
+        ratings
+    };
+
+    let forward = TestEnv::new("test_fit_forward");
+    let backward = TestEnv::new("test_fit_backward");
+    for (env, order) in [(&forward, games.to_vec()), (&backward, games.iter().rev().cloned().collect())] {
+        env.activate();
+        env.cmd().arg("fit").assert().failure();
+        for id in ["test_player1", "test_player2", "test_player3"] {
+            env.cmd().arg("add").arg(id).assert().success();
+        }
+        for game in order {
+            env.cmd().arg("vs").args(game).assert().success();
+        }
+    }
+
+    // Online ratings depend on the order of the games, the fitted ones don't
+    assert_ne!(forward.players(), backward.players());
+    assert_eq!(fitted(&forward).len(), 3);
+    assert_eq!(fitted(&forward), fitted(&backward));
+}
+
+#[test]
+fn test_head_bootstrap_is_reproducible() {
+    let env = TestEnv::new("test_head_bootstrap");
+    env.activate();
+    for id in ["test_player1", "test_player2", "test_player3"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+    env.cmd().arg("head").arg("--bootstrap").arg("100").assert().failure();
+    env.cmd().arg("head").arg("--seed").arg("1").assert().failure();
+
+    for game in [
+        ["test_player1", "gt", "test_player2"],
+        ["test_player2", "gt", "test_player3"],
+        ["test_player1", "eq", "test_player3"],
+        ["test_player3", "lt", "test_player1"],
+    ] {
+        env.cmd().arg("vs").args(game).assert().success();
+    }
+
+    let bootstrap = |args: &[&str]| {
+        let output = env.cmd().arg("head").arg("--bootstrap").arg("200").args(args).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let replayed = bootstrap(&["--seed", "7"]);
+    assert!(replayed.contains("Rank range"));
+    assert!(replayed.contains("glicko2 replay"));
+    assert_eq!(replayed.matches("test_player").count(), 3);
+    assert_eq!(replayed, bootstrap(&["--seed", "7"]));
+
+    let fitted = bootstrap(&["--seed", "7", "--fit", "bradley-terry"]);
+    assert!(fitted.contains("Bradley-Terry fit"));
+    assert_eq!(fitted, bootstrap(&["--seed", "7", "--fit", "bradley-terry"]));
+
+    // With a single match every resample is that match, so replaying it reproduces the
+    // project's own ratings, margin included
+    let single = TestEnv::new("test_head_bootstrap_single");
+    single.activate();
+    for id in ["test_player1", "test_player2"] {
+        single.cmd().arg("add").arg(id).assert().success();
+    }
+    single.cmd().arg("vs").arg("test_player1").arg("3-0").arg("test_player2").assert().success();
+    let output = single.cmd().args(["--format", "json", "head", "--bootstrap", "20"]).output().unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["players"][0]["id"], "test_player1");
+    assert_eq!(json["players"][0]["median"], single.elo_data()["players"]["test_player1"]["rating"]["rating"]);
+}
+
+#[test]
+fn test_rank_free_for_all() {
+    let env = TestEnv::new("test_rank_free_for_all");
+    env.activate();
+    for id in ["test_player1", "test_player2", "test_player3", "test_player4"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+
+    env.cmd().arg("rank").arg("test_player1 > test_player2 > test_player1").assert().failure();
+    env.cmd().arg("rank").arg("test_player1 > > test_player2").assert().failure();
+    env.cmd().arg("rank").arg("test_player1 > unknown").assert().failure();
+    env.cmd().arg("rank").arg("test_player1").assert().failure();
+    env.cmd().arg("rank").arg("test_player1 > test_player2 = test_player3 > test_player4").assert().success();
+
+    // Every player is counted as having played each opponent once
+    let players = env.elo_data()["players"].clone();
+    let stats = |id: &str| {
+        let player = &players[id];
+        (player["wins"].as_u64().unwrap(), player["losses"].as_u64().unwrap(), player["ties"].as_u64().unwrap())
+    };
+    assert_eq!(stats("test_player1"), (3, 0, 0));
+    assert_eq!(stats("test_player2"), (1, 1, 1));
+    assert_eq!(stats("test_player3"), (1, 1, 1));
+    assert_eq!(stats("test_player4"), (0, 3, 0));
+    let rating = |id: &str| players[id]["rating"]["rating"].as_f64().unwrap();
+    assert!(rating("test_player1") > rating("test_player2"));
+    assert!(rating("test_player3") > rating("test_player4"));
+
+    let output = env.cmd().arg("match").arg("list").output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("test_player1 > test_player2 = test_player3 > test_player4"));
+    env.cmd().arg("match").arg("edit").arg("1").arg("--result").arg("lt").assert().failure();
+
+    // The ranking replays to the same ratings and can be undone like any other match
+    env.cmd().arg("recompute").assert().success();
+    assert_eq!(env.elo_data()["players"], players);
+    env.cmd().arg("undo").assert().success();
+    assert_eq!(env.elo_data()["players"]["test_player1"]["wins"], 0);
+}
+
+#[test]
+fn test_team_vs() {
+    let env = TestEnv::with_args("test_team_vs", &["--algorithm", "weng-lin"]);
+    env.activate();
+    for id in ["test_player1", "test_player2", "test_player3", "test_player4"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+
+    env.cmd().arg("team-vs").arg("test_player1,test_player2").arg("gt").arg("test_player2").assert().failure();
+    env.cmd().arg("team-vs").arg("test_player1").arg("gt").arg("unknown").assert().failure();
+    env.cmd().arg("team-vs").arg("test_player1,test_player2").arg("gt").arg("test_player3,test_player4").assert().success();
+    env.cmd().arg("team-vs").arg("test_player1").arg("eq").arg("test_player3,test_player4").assert().success();
+
+    // Every member gets the team's result
+    let players = env.elo_data()["players"].clone();
+    for (id, wins, losses, ties) in [
+        ("test_player1", 1, 0, 1),
+        ("test_player2", 1, 0, 0),
+        ("test_player3", 0, 1, 1),
+        ("test_player4", 0, 1, 1),
+    ] {
+        assert_eq!(players[id]["wins"], wins);
+        assert_eq!(players[id]["losses"], losses);
+        assert_eq!(players[id]["ties"], ties);
+    }
+    let rating = |id: &str| players[id]["rating"]["rating"].as_f64().unwrap();
+    assert!(rating("test_player2") > rating("test_player3"));
+
+    // Team games replay like any other match, under every algorithm
+    env.cmd().arg("recompute").assert().success();
+    assert_eq!(env.elo_data()["players"], players);
+    env.cmd().arg("match").arg("edit").arg("1").arg("--result").arg("lt").assert().success();
+    assert!(env.elo_data()["players"]["test_player3"]["rating"]["rating"].as_f64().unwrap() > rating("test_player3"));
+    env.cmd().arg("recompute").arg("--algorithm").arg("glicko2").assert().success();
+    assert_eq!(env.elo_data()["players"]["test_player3"]["wins"], 1);
+}
+
+#[test]
+fn test_scores_and_graded_results() {
+    let env = TestEnv::with_args("test_scores_and_graded_results", &["--algorithm", "elo"]);
+    env.activate();
+    for id in ["test_player1", "test_player2"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+    env.cmd().arg("vs").arg("test_player1").arg("eq:strong").arg("test_player2").assert().failure();
+    env.cmd().arg("vs").arg("test_player1").arg("3-x").arg("test_player2").assert().failure();
+
+    // A blowout moves the ratings further than a narrow win, which counts as a normal game
+    let gain = |result: &str| {
+        env.cmd().arg("vs").arg("test_player1").arg(result).arg("test_player2").assert().success();
+        let rating = env.elo_data()["players"]["test_player1"]["rating"]["rating"].as_f64().unwrap();
+        env.cmd().arg("undo").assert().success();
+        rating - 1000.0
+    };
+    let (normal, narrow, blowout, strong, weak) = (gain("gt"), gain("6-5"), gain("10-0"), gain("gt:strong"), gain("gt:weak"));
+    assert!((normal - narrow).abs() < 1e-9);
+    assert!(blowout > normal);
+    assert!(strong > normal && normal > weak && weak > 0.0);
+
+    env.cmd().arg("vs").arg("test_player1").arg("10-0").arg("test_player2").assert().success();
+    env.cmd().arg("vs").arg("test_player2").arg("2-2").arg("test_player1").assert().success();
+    let player1 = env.elo_data()["players"]["test_player1"].clone();
+    assert_eq!(player1["points_for"], 12);
+    assert_eq!(player1["points_against"], 2);
+    assert_eq!(player1["ties"], 1);
+    let output = env.cmd().arg("info").arg("test_player1").output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Points for: 12 | Points against: 2"));
+
+    // The raw score is kept in the history, so edits and replays honour it
+    env.cmd().arg("match").arg("edit").arg("1").arg("--result").arg("1-0").assert().success();
+    assert_eq!(env.elo_data()["players"]["test_player1"]["points_for"], 3);
+    let output = env.cmd().arg("match").arg("list").output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("test_player1 1-0 test_player2"));
+}
+
+#[test]
+fn test_series() {
+    let sequential = TestEnv::new("test_series_sequential");
+    let one_by_one = TestEnv::new("test_series_one_by_one");
+    let period = TestEnv::new("test_series_period");
+    for env in [&sequential, &one_by_one, &period] {
+        env.activate();
+        for id in ["test_player1", "test_player2"] {
+            env.cmd().arg("add").arg(id).assert().success();
+        }
+    }
+
+    // Invalid series are rejected as a whole
+    sequential.cmd().args(["series", "test_player1", "test_player2", "--results", "gt,xx"]).assert().failure();
+    sequential.cmd().args(["series", "test_player1", "unknown", "--results", "gt"]).assert().failure();
+    assert_eq!(sequential.elo_data()["players"]["test_player1"]["wins"], 0);
+
+    // By default a series is the same as recording every game on its own
+    sequential.cmd().args(["series", "test_player1", "test_player2", "--results", "gt,gt,lt,eq,gt"]).assert().success();
+    for result in ["gt", "gt", "lt", "eq", "gt"] {
+        one_by_one.cmd().arg("vs").arg("test_player1").arg(result).arg("test_player2").assert().success();
+    }
+    assert_eq!(sequential.players(), one_by_one.players());
+
+    // As one rating period it is stored as a single match with the same stats
+    period
+        .cmd()
+        .args(["series", "test_player1", "test_player2", "--results", "gt,gt,lt,eq,gt", "--single-period"])
+        .assert()
+        .success();
+    let players = period.elo_data()["players"].clone();
+    assert_eq!(players["test_player1"]["wins"], 3);
+    assert_eq!(players["test_player1"]["losses"], 1);
+    assert_eq!(players["test_player2"]["ties"], 1);
+    assert_ne!(period.players(), sequential.players());
+    period.cmd().arg("recompute").assert().success();
+    assert_eq!(period.elo_data()["players"], players);
+    period.cmd().arg("undo").assert().success();
+    assert_eq!(period.elo_data()["players"]["test_player1"]["wins"], 0);
+}
+
+#[test]
+fn test_rating_periods() {
+    let env = TestEnv::new("test_rating_periods");
+    env.activate();
+    for id in ["test_player1", "test_player2", "test_player3"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+    env.cmd().args(["vs", "test_player1", "gt", "test_player3"]).assert().success();
+    env.cmd().args(["period", "close"]).assert().failure();
+
+    // Games in an open period count right away but are rated only when it closes
+    env.cmd().args(["period", "start"]).assert().success();
+    env.cmd().args(["period", "start"]).assert().failure();
+    let before = env.elo_data()["players"].clone();
+    for _ in 0..2 {
+        env.cmd().args(["vs", "test_player1", "gt", "test_player2"]).assert().success();
+    }
+    let pending = env.elo_data()["players"].clone();
+    assert_eq!(pending["test_player1"]["wins"], 3);
+    assert_eq!(pending["test_player1"]["rating"], before["test_player1"]["rating"]);
+
+    env.cmd().args(["period", "close"]).assert().success();
+    let closed = env.elo_data()["players"].clone();
+    let rating = |players: &serde_json::Value, id: &str, field: &str| players[id]["rating"][field].as_f64().unwrap();
+    assert!(rating(&closed, "test_player1", "rating") > rating(&before, "test_player1", "rating"));
+    // A player who sat the period out becomes less certain
+    assert!(rating(&closed, "test_player3", "deviation") > rating(&before, "test_player3", "deviation"));
+    assert_eq!(env.elo_data()["periods"].as_array().unwrap().len(), 2);
+
+    env.cmd().arg("recompute").assert().success();
+    assert_eq!(env.elo_data()["players"], closed);
+
+    // With a period length, periods that ran out are closed when the next game is recorded
+    env.cmd().args(["config", "set", "rating_period_days", "1"]).assert().success();
+    let mut data = env.elo_data();
+    let three_days_ago = chrono::Utc::now() - chrono::Duration::days(3) - chrono::Duration::hours(1);
+    data["periods"][1]["started_at"] = serde_json::Value::String(three_days_ago.to_rfc3339());
+    std::fs::write(env.elo_path(), serde_json::to_string(&data).unwrap()).unwrap();
+    env.cmd().args(["vs", "test_player2", "gt", "test_player3"]).assert().success();
+    let periods = env.elo_data()["periods"].as_array().unwrap().clone();
+    assert_eq!(periods.len(), 5);
+    assert!(periods[3]["closed_at"].is_string());
+    assert!(periods[4]["closed_at"].is_null());
+
+    env.cmd().args(["period", "stop"]).assert().success();
+    let stopped = env.elo_data()["players"].clone();
+    assert_eq!(stopped["test_player2"]["wins"], 1);
+    env.cmd().arg("recompute").assert().success();
+    assert_eq!(env.elo_data()["players"], stopped);
+}
+
+#[test]
+fn test_deviation_decay() {
+    let env = TestEnv::new("test_deviation_decay");
+    env.activate();
+    for id in ["test_player1", "test_player2"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+    for _ in 0..5 {
+        env.cmd().args(["vs", "test_player1", "gt", "test_player2"]).assert().success();
+    }
+    assert!(env.elo_data()["players"]["test_player1"]["last_played"].is_string());
+
+    let info = |env: &TestEnv| {
+        let output = env.cmd().args(["info", "test_player1"]).output().unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let deviation = |info: &str| -> i64 {
+        let rd = info.split(" | ").find_map(|field| field.strip_prefix("RD: ")).unwrap();
+        rd.parse().unwrap()
+    };
+    let recent = info(&env);
+    assert!(recent.contains("Last played: "));
+
+    // Three months without a game widen the deviation shown, but only once decay is enabled
+    let mut data = env.elo_data();
+    let three_months_ago = chrono::Utc::now() - chrono::Duration::days(90);
+    data["players"]["test_player1"]["last_played"] = serde_json::Value::String(three_months_ago.to_rfc3339());
+    std::fs::write(env.elo_path(), serde_json::to_string(&data).unwrap()).unwrap();
+    assert_eq!(deviation(&info(&env)), deviation(&recent));
+    env.cmd().args(["config", "set", "deviation_decay", "10"]).assert().success();
+    let inactive = info(&env);
+    assert!(inactive.contains(&format!("Last played: {}", three_months_ago.format("%Y-%m-%d"))));
+    assert!(deviation(&inactive) > deviation(&recent));
+    // The stored rating keeps its deviation until the next game
+    assert_eq!(env.elo_data()["players"]["test_player1"]["rating"], data["players"]["test_player1"]["rating"]);
+}
+
+#[test]
+fn test_import() {
+    let imported = TestEnv::new("test_import");
+    let recorded = TestEnv::new("test_import_recorded");
+    imported.activate();
+    let csv = imported.home.join("matches.csv");
+    std::fs::write(
+        &csv,
+        "player1,player2,result,timestamp,weight\n\
+         test_player1,test_player2,gt,2025-01-01,80\n\
+         test_player2,test_player3,3-1,2025-01-02 10:00:00,75\n\
+         test_player3,test_player1,eq,,\n",
+    )
+    .unwrap();
+
+    // Unknown players are rejected unless they may be created, and a dry run changes nothing
+    imported.cmd().arg("import").arg(&csv).assert().failure();
+    let output = imported.cmd().arg("import").arg(&csv).args(["--create-players", "--dry-run"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Would import 3 games"));
+    assert!(imported.elo_data()["players"].as_object().unwrap().is_empty());
+
+    // A single invalid row keeps the whole file out
+    let jsonl = imported.home.join("judgments.jsonl");
+    std::fs::write(
+        &jsonl,
+        "{\"a\": \"test_player1\", \"b\": \"test_player2\", \"winner\": \"lt\"}\n\
+         {\"a\": \"test_player1\", \"b\": \"test_player2\", \"winner\": \"xx\"}\n",
+    )
+    .unwrap();
+    let columns = ["--player1-column", "a", "--player2-column", "b", "--result-column", "winner"];
+    imported.cmd().arg("import").arg(&jsonl).args(columns).arg("--create-players").assert().failure();
+    assert!(imported.elo_data()["players"].as_object().unwrap().is_empty());
+
+    // Importing is the same as recording every game with `vs`, ignoring unrelated columns
+    imported.cmd().arg("import").arg(&csv).arg("--create-players").assert().success();
+    recorded.activate();
+    for id in ["test_player1", "test_player2", "test_player3"] {
+        recorded.cmd().arg("add").arg(id).assert().success();
+    }
+    for (id1, result, id2) in [
+        ("test_player1", "gt", "test_player2"),
+        ("test_player2", "3-1", "test_player3"),
+        ("test_player3", "eq", "test_player1"),
+    ] {
+        recorded.cmd().arg("vs").arg(id1).arg(result).arg(id2).assert().success();
+    }
+    assert_eq!(imported.players(), recorded.players());
+    assert_eq!(imported.elo_data()["players"]["test_player2"]["last_played"], "2025-01-02T10:00:00Z");
+
+    // Weights are only read from the column given
+    let weighted = TestEnv::new("test_import_weighted");
+    weighted.activate();
+    weighted.cmd().arg("import").arg(&csv).args(["--create-players", "--weight-column", "weight"]).assert().success();
+    let history = std::fs::read_to_string(weighted.elo_path().with_file_name("history.jsonl")).unwrap();
+    let weights: Vec<f64> = history
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["weight"].as_f64().unwrap_or(1.0))
+        .collect();
+    assert_eq!(weights, [80.0, 75.0, 1.0]);
+}
+
+#[test]
+fn test_arena_import() {
+    let env = TestEnv::new("test_arena_import");
+    env.activate();
+    let battles = env.home.join("battles.jsonl");
+    std::fs::write(
+        &battles,
+        "{\"question_id\": \"q1\", \"model_a\": \"model1\", \"model_b\": \"model2\", \"winner\": \"model_a\", \"tstamp\": 1682351591.25}\n\
+         {\"question_id\": \"q2\", \"model_a\": \"model3\", \"model_b\": \"model1\", \"winner\": \"tie (bothbad)\"}\n\
+         {\"question_id\": \"q1\", \"model_a\": \"model1\", \"model_b\": \"model2\", \"winner\": \"model_a\"}\n\
+         {\"question_id\": \"q3\", \"model_a\": \"model2\", \"model_b\": \"model3\", \"winner\": \"model_b\"}\n",
+    )
+    .unwrap();
+    let import = |env: &TestEnv| {
+        let output = env
+            .cmd()
+            .args(["import", "--from", "arena"])
+            .arg(&battles)
+            .args(["--dedupe-by", "question_id", "--create-players"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = import(&env);
+    assert!(stdout.contains("Imported 3 games"));
+    assert!(stdout.contains("1 with both sides bad"));
+    assert!(stdout.contains("Skipped 1 duplicate games"));
+    let data = env.elo_data();
+    assert_eq!(data["players"]["model1"]["wins"], 1);
+    assert_eq!(data["players"]["model1"]["ties"], 1);
+    assert_eq!(data["players"]["model3"]["wins"], 1);
+
+    // The "both bad" distinction is kept with the match
+    let history = std::fs::read_to_string(env.elo_path().with_file_name("history.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = history.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records[0]["played_at"], "2023-04-24T15:53:11.250Z");
+    assert_eq!(records[1]["both_bad"], true);
+    assert_eq!(records[1]["source_id"], "q2");
+    assert!(records[2].get("both_bad").is_none());
+
+    // Importing the same battles again skips all of them
+    assert!(import(&env).contains("Imported 0 games"));
+    assert_eq!(env.elo_data()["players"], data["players"]);
+
+    // Arena winners are checked too
+    std::fs::write(&battles, "{\"model_a\": \"model1\", \"model_b\": \"model2\", \"winner\": \"model_c\"}\n").unwrap();
+    env.cmd().args(["import", "--from", "arena"]).arg(&battles).assert().failure();
+}
+
+#[test]
+fn test_export() {
+    let env = TestEnv::new("test_export");
+    env.activate();
+    for id in ["test_player1", "test_player2", "test_player3"] {
+        env.cmd().arg("add").arg(id).assert().success();
+    }
+    env.cmd().args(["vs", "test_player2", "gt", "test_player1"]).assert().success();
+    let export = |format: &str| {
+        let output = env.cmd().args(["export", "--to", format]).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    // JSON covers every player, best first
+    let rows: Vec<serde_json::Value> = serde_json::from_str(&export("json")).unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["rank"], 1);
+    assert_eq!(rows[0]["id"], "test_player2");
+    assert_eq!(rows[0]["games"], 1);
+    assert_eq!(rows[2]["id"], "test_player1");
+    assert_eq!(rows[2]["losses"], 1);
+
+    let csv = export("csv");
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[0],
+        "rank,id,rating,deviation,volatility,wins,losses,ties,games,points_for,points_against,last_played"
+    );
+    assert!(lines[1].starts_with("1,test_player2,"));
+
+    // Every format has the same columns: JSON fields match the CSV header, and Markdown
+    // and LaTeX give them the same titles
+    let mut keys: Vec<&str> = rows[0].as_object().unwrap().keys().map(String::as_str).collect();
+    let mut header: Vec<&str> = lines[0].split(',').collect();
+    keys.sort_unstable();
+    header.sort_unstable();
+    assert_eq!(keys, header);
+    let titles = [
+        "Rank", "Player", "Rating", "RD", "Volatility", "Wins", "Losses", "Ties", "Games", "Points for",
+        "Points against", "Last played",
+    ];
+    let markdown = export("markdown");
+    assert_eq!(markdown.lines().count(), 5);
+    assert_eq!(markdown.lines().next().unwrap(), format!("| {} |", titles.join(" | ")));
+
+    // LaTeX escapes special characters in player IDs
+    let latex = export("latex");
+    assert!(latex.starts_with("\\begin{tabular}"));
+    assert_eq!(latex.lines().nth(2).unwrap(), format!("{} \\\\", titles.join(" & ")));
+    assert!(latex.contains("test\\_player2"));
+
+    // Writing to a file gives the same leaderboard
+    let path = env.home.join("leaderboard.csv");
+    env.cmd().args(["export", "--output"]).arg(&path).assert().success();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), csv);
+
+    // Every algorithm exports the same columns, leaving out what it doesn't track, and
+    // players with equal ratings are listed by ID
+    let elo = TestEnv::with_args("test_export_elo", &["--algorithm", "elo"]);
+    elo.activate();
+    for id in ["test_player3", "test_player1", "test_player2"] {
+        elo.cmd().arg("add").arg(id).assert().success();
+    }
+    let output = elo.cmd().args(["export", "--to", "csv"]).output().unwrap();
+    let elo_csv = String::from_utf8_lossy(&output.stdout).to_string();
+    let elo_lines: Vec<&str> = elo_csv.lines().collect();
+    assert_eq!(elo_lines[0], lines[0]);
+    assert_eq!(elo_lines[1], "1,test_player1,1000,,,0,0,0,0,0,0,");
+    assert!(elo_lines[2].starts_with("2,test_player2,"));
+    let output = elo.cmd().args(["export", "--to", "json"]).output().unwrap();
+    let rows: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rows[0]["deviation"], serde_json::Value::Null);
+    assert_eq!(rows[0].as_object().unwrap().len(), 12);
+}
+
+#[test]
+fn test_json_output() {
+    let env = TestEnv::new("test_json_output");
+    let json = |args: &[&str]| {
+        let output = env.cmd().args(["--format", "json"]).args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let stdout: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (stdout, String::from_utf8_lossy(&output.stderr).to_string())
+    };
+
+    let (activated, _) = json(&["activate", &env.name]);
+    assert_eq!(activated["active_project"], env.name.as_str());
+    assert_eq!(json(&["whoami"]).0["active_project"], env.name.as_str());
+    for id in ["test_player1", "test_player2"] {
+        let (added, _) = json(&["add", id]);
+        assert_eq!(added["player"]["id"], id);
+    }
+
+    // Results go to stdout as JSON, messages for people to stderr
+    let (recorded, messages) = json(&["vs", "test_player1", "gt", "test_player2"]);
+    assert!(messages.contains("Game recorded!"));
+    let record = &recorded["matches"][0];
+    assert_eq!(record["id"], 1);
+    assert_eq!(record["result"], "player1_wins");
+    assert!(record["player1_rating"]["after"]["rating"].as_f64().unwrap() > 1500.0);
+
+    // Every match has the same keys for its type, `null` where there is no value
+    assert_eq!(record["type"], "duel");
+    assert_eq!(record["margin"], "normal");
+    assert_eq!(record["weight"], 1.0);
+    assert_eq!(record["period"], serde_json::Value::Null);
+    assert_eq!(record["source_id"], serde_json::Value::Null);
+    assert_eq!(record["both_bad"], false);
+    json(&["add", "test_player3"]);
+    let (ranked, _) = json(&["rank", "test_player3 > test_player1 > test_player2"]);
+    assert_eq!(ranked["matches"][0]["type"], "ranking");
+    assert_eq!(ranked["matches"][0]["period"], serde_json::Value::Null);
+    json(&["undo"]);
+    json(&["remove", "test_player3"]);
+
+    let (head, _) = json(&["head"]);
+    assert_eq!(head["players"][0]["id"], "test_player1");
+    assert!(head["players"][0]["last_played"].is_string());
+    assert_eq!(head["players"][0]["rank"], 1);
+    assert_eq!(head["players"][1]["losses"], 1);
+
+    let (info, _) = json(&["info", "test_player2"]);
+    assert_eq!(info["player"]["rank"], 2);
+    assert_eq!(info["player"]["games"], 1);
+
+    let (prediction, _) = json(&["predict", "test_player1", "test_player2"]);
+    assert!(prediction["player1_wins"].as_f64().unwrap() > 0.5);
+
+    let (matches, _) = json(&["match", "list"]);
+    assert_eq!(matches["matches"].as_array().unwrap().len(), 1);
+    assert_eq!(json(&["config", "get", "tau"]).0["tau"], 0.5);
+    let (listed, _) = json(&["list"]);
+    assert!(listed["projects"].as_array().unwrap().contains(&env.name.as_str().into()));
+
+    // Export keeps its own formats and defaults to JSON
+    let (exported, _) = json(&["export"]);
+    assert_eq!(exported[0]["id"], "test_player1");
+    let output = env.cmd().args(["--format", "json", "export", "--to", "csv"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("rank,id,"));
+
+    // The flag can also follow the command, including nested ones
+    let after = |args: &[&str]| {
+        let output = env.cmd().args(args).args(["--format", "json"]).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+    assert_eq!(after(&["head"]), head);
+    assert_eq!(after(&["match", "list"])["matches"], matches["matches"]);
+    assert_eq!(after(&["export"]), exported);
+}
+
+#[test]
+fn test_error_exit_codes() {
+    let env = TestEnv::new("test_error_exit_codes");
+    let code = |args: &[&str]| env.cmd().args(args).output().unwrap().status.code();
+
+    env.cmd().arg("deactivate").assert().success();
+    assert_eq!(code(&["head"]), Some(3));
+    assert_eq!(code(&["activate", "test_missing_project"]), Some(4));
+    assert_eq!(code(&["init", &env.name]), Some(5));
+    env.activate();
+    env.cmd().args(["add", "test_player1"]).assert().success();
+    assert_eq!(code(&["add", "test_player1"]), Some(7));
+    assert_eq!(code(&["add", " "]), Some(8));
+    assert_eq!(code(&["vs", "test_player1", "gt", "test_player2"]), Some(6));
+    assert_eq!(code(&["vs", "test_player1", "gt", "test_player1"]), Some(9));
+    // Unknown players are reported before self-matches, the same way for every command
+    assert_eq!(code(&["series", "test_player2", "test_player2", "--results", "gt"]), Some(6));
+    assert_eq!(code(&["series", "test_player1", "test_player1", "--results", "gt"]), Some(9));
+    assert_eq!(code(&["merge", "test_player2", "test_player2"]), Some(6));
+    assert_eq!(code(&["merge", "test_player1", "test_player1"]), Some(9));
+    assert_eq!(code(&["rename", "test_player2", "test_player2"]), Some(6));
+    assert_eq!(code(&["info", "test_player2"]), Some(6));
+    assert_eq!(code(&["match", "delete", "1"]), Some(10));
+    assert_eq!(code(&["config", "set", "not_a_setting", "1"]), Some(11));
+    assert_eq!(code(&["config", "set", "draw_probability", "2"]), Some(12));
+    // Failed commands leave the project untouched
+    assert_eq!(env.elo_data()["players"].as_object().unwrap().len(), 1);
+
+    // With --format json the error is also reported on stdout
+    let output = env.cmd().args(["--format", "json", "add", "test_player1"]).output().unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(error["error"]["kind"], "duplicate_player");
+}
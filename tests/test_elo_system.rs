@@ -0,0 +1,97 @@
+use belo::elo_system::{EloSystem, MatchResult, SortBy};
+use belo::error::BeloError;
+use belo::rating::RatingAlgorithm;
+use clap::ValueEnum;
+
+fn result(result: &str) -> MatchResult {
+    result.parse().unwrap()
+}
+
+/// A system with the given players and no games yet.
+fn system_with(ids: &[&str]) -> EloSystem {
+    let mut system = EloSystem::new();
+    for id in ids {
+        system.add_player(id).unwrap();
+    }
+    system
+}
+
+#[test]
+fn test_json_round_trip() {
+    let mut system = system_with(&["alice", "bob", "carol"]);
+    let mut settings = system.settings().clone();
+    settings.set("default_rating", 1200.0).unwrap();
+    system.set_settings(settings);
+    system.record_game("alice", result("gt"), "bob").unwrap();
+    system.start_period().unwrap();
+    system.record_game("bob", result("eq"), "carol").unwrap();
+    system.close_period(false).unwrap();
+    // Left open, so the restored system still has to rate this game
+    system.record_game("carol", result("gt"), "alice").unwrap();
+
+    let json = system.to_json().unwrap();
+    let mut restored = EloSystem::from_json(&json).unwrap();
+    // Players are kept in a map, so compare the documents rather than their text
+    let value = |json: &str| serde_json::from_str::<serde_json::Value>(json).unwrap();
+    assert_eq!(value(&restored.to_json().unwrap()), value(&json));
+    assert_eq!(restored.history().len(), 3);
+    assert_eq!(restored.settings().get("default_rating").unwrap(), 1200.0);
+    assert_eq!(restored.settings().changes.len(), 1);
+
+    let (period, rated) = restored.close_period(true).unwrap();
+    assert_eq!(period.id, 2);
+    assert_eq!(rated, 1);
+    assert!(restored.start_period().is_ok());
+}
+
+#[test]
+fn test_record_games_is_all_or_nothing() {
+    let mut system = system_with(&["alice", "bob", "carol"]);
+    let game = |id1: &str, result_str: &str, id2: &str| (id1.to_string(), result(result_str), id2.to_string());
+    let before = system.to_json().unwrap();
+
+    // A bad row anywhere in the batch records none of the games before it
+    let unknown = [game("alice", "gt", "bob"), game("bob", "gt", "dave")];
+    let error = system.record_games(&unknown).unwrap_err();
+    assert!(matches!(error.downcast_ref(), Some(BeloError::UnknownPlayer(id)) if id == "dave"));
+    let self_match = [game("alice", "gt", "bob"), game("carol", "eq", "carol")];
+    let error = system.record_games(&self_match).unwrap_err();
+    assert!(matches!(error.downcast_ref(), Some(BeloError::SelfMatch)));
+    assert_eq!(system.to_json().unwrap(), before);
+    assert!(system.history().is_empty());
+
+    let deltas = system.record_games(&[game("alice", "gt", "bob"), game("bob", "lt", "carol")]).unwrap();
+    assert_eq!(system.history().len(), 2);
+    assert!(deltas[0].0 > 0.0 && deltas[0].1 < 0.0);
+    assert!(deltas[1].0 < 0.0 && deltas[1].1 > 0.0);
+}
+
+#[test]
+fn test_win_probabilities() {
+    for &algorithm in RatingAlgorithm::value_variants() {
+        let mut system = EloSystem::with_algorithm(algorithm);
+        for id in ["alice", "bob", "carol"] {
+            system.add_player(id).unwrap();
+        }
+        system.record_game("alice", result("gt"), "bob").unwrap();
+        system.record_game("bob", result("gt"), "carol").unwrap();
+        system.record_game("alice", result("eq"), "carol").unwrap();
+
+        let n = system.player_count();
+        let probabilities = system.win_probabilities(SortBy::Rating);
+        assert_eq!(probabilities.len(), n * n);
+        for i in 0..n {
+            assert!(probabilities[i * n + i].is_nan());
+            for j in (i + 1)..n {
+                let (p, q) = (probabilities[i * n + j], probabilities[j * n + i]);
+                // Players are sorted best first, and only TrueSkill leaves room for a draw
+                assert!(p > q, "{:?}: {} against {}", algorithm, p, q);
+                if algorithm == RatingAlgorithm::TrueSkill {
+                    assert!(p + q < 1.0, "{:?}: {} + {}", algorithm, p, q);
+                } else {
+                    assert!((p + q - 1.0).abs() < 1e-9, "{:?}: {} + {}", algorithm, p, q);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Helpers for every script: `cli` runs the `belo` binary in the same home directory and
+/// `raises` checks that a call fails with the given exception, returning its message.
+const PRELUDE: &str = r#"
+import os, subprocess
+import belo
+
+def cli(*args):
+    return subprocess.run([os.environ["BELO"], *args], check=True, capture_output=True, text=True).stdout
+
+def raises(exception, call, *args):
+    try:
+        call(*args)
+    except exception as e:
+        return str(e)
+    raise AssertionError(f"{call.__name__}{args} didn't raise {exception.__name__}")
+"#;
+
+/// Runs a Python script against the extension module built next to this test, from a
+/// temporary directory that doubles as the home directory. Fails the test if the script
+/// does, and skips it when Python or the built module isn't available.
+fn run_python(test_name: &str, script: &str) {
+    // Tests live in target/<profile>/deps, the library in target/<profile>.
+    let exe = std::env::current_exe().unwrap();
+    let profile_dir = exe.parent().unwrap().parent().unwrap();
+    let library = ["libbelo.so", "libbelo.dylib"]
+        .iter()
+        .map(|name| profile_dir.join(name))
+        .find(|path| path.exists());
+    let Some(library) = library else {
+        eprintln!("Skipping {}: the belo library hasn't been built (run `cargo build` first).", test_name);
+        return;
+    };
+    if Command::new("python3").arg("--version").output().is_err() {
+        eprintln!("Skipping {}: python3 isn't installed.", test_name);
+        return;
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let dir: PathBuf = std::env::temp_dir().join("belo_tests").join(format!("{}_{}", test_name, now));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(library, dir.join("belo.so")).unwrap();
+    let output = Command::new("python3")
+        .arg("-c")
+        .arg(format!("{}{}", PRELUDE, script))
+        .current_dir(&dir)
+        .env("HOME", &dir)
+        .env("BELO", env!("CARGO_BIN_EXE_belo"))
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_python_systems_are_independent() {
+    let script = r#"
+chess, go = belo.EloSystem(), belo.EloSystem("elo")
+for system in (chess, go):
+    system.add_player("alice")
+    system.add_player("bob")
+chess.record_game("alice", "gt", "bob")
+
+assert chess.info("alice")["wins"] == 1
+assert go.info("alice")["wins"] == 0
+assert go.info("alice")["elo"] == 1000
+raises(belo.UnknownPlayerError, go.record_game, "alice", "gt", "carol")
+"#;
+    run_python("test_python_systems_are_independent", script);
+}
+
+#[test]
+fn test_python_projects() {
+    let script = r#"
+import json
+
+cli("init", "league")
+cli("add", "alice")
+cli("add", "bob")
+cli("deactivate")
+raises(belo.NoActiveProjectError, belo.active_project)
+cli("activate", "league")
+
+league = belo.active_project()
+assert league.project == "league"
+assert len(league) == 2
+league.record_game("alice", "gt", "bob")
+# Changes stay in memory until saved
+assert belo.open_project("league").info("alice")["wins"] == 0
+league.save()
+assert belo.open_project("league").info("alice")["wins"] == 1
+assert json.loads(cli("--format", "json", "info", "alice"))["player"]["wins"] == 1
+assert len(json.loads(cli("--format", "json", "match", "list"))["matches"]) == 1
+raises(belo.UnknownProjectError, belo.open_project, "missing")
+
+# A system that isn't a project's can only be saved to a path
+unnamed = belo.EloSystem()
+assert unnamed.project is None
+message = raises(belo.BeloError, unnamed.save)
+assert "pass a path" in message, message
+unnamed.save("unnamed.json")
+assert len(belo.EloSystem.load("unnamed.json")) == 0
+"#;
+    run_python("test_python_projects", script);
+}
+
+#[test]
+fn test_python_global_functions() {
+    let script = r#"
+belo.init_state("elo")
+belo.add_player("alice")
+belo.add_player("bob")
+belo.record_game("alice", "gt", "bob")
+belo.record_games(["bob"], ["lt"], ["alice"])
+belo.record_series("alice", "bob", ["gt", "eq"])
+
+assert belo.get_top_n(1)[0]["id"] == "alice"
+assert list(belo.leaderboard()["games"]) == [4, 4]
+prediction = belo.predict("alice", "bob")
+assert prediction["win"] > prediction["loss"]
+raises(belo.SelfMatchError, belo.record_game, "alice", "gt", "alice")
+# Objects don't see the shared leaderboard
+assert len(belo.EloSystem()) == 0
+
+belo.save_state("state.json")
+belo.init_state()
+assert list(belo.leaderboard()["id"]) == []
+belo.load_state("state.json")
+assert list(belo.leaderboard()["id"]) == ["alice", "bob"]
+belo.rename_player("bob", "carol")
+belo.add_player("alice_dup")
+belo.merge_players("alice_dup", "alice")
+belo.remove_player("carol")
+assert list(belo.leaderboard()["id"]) == ["alice"]
+"#;
+    run_python("test_python_global_functions", script);
+}
+
+#[test]
+fn test_python_pickle() {
+    let script = r#"
+import json, pickle
+
+cli("init", "league")
+for id in ("alice", "bob", "carol"):
+    cli("add", id)
+cli("vs", "alice", "gt", "bob")
+cli("period", "start")
+cli("vs", "bob", "eq", "carol")
+cli("period", "close")
+# Left open, so this game is still waiting to be rated
+cli("vs", "carol", "gt", "alice")
+league = belo.open_project("league")
+
+def columns(system):
+    return {key: list(column) for key, column in system.leaderboard().items()}
+
+copy = pickle.loads(pickle.dumps(league))
+assert columns(copy) == columns(league)
+assert json.loads(copy.to_json()) == json.loads(league.to_json())
+state = json.loads(copy.to_json())
+assert len(state["history"]) == 3
+assert [period["closed_at"] is None for period in state["periods"]] == [False, True]
+# A copy isn't tied to the project, so it can't overwrite it
+assert copy.project is None
+
+league.save("league.json")
+loaded = belo.EloSystem.load("league.json")
+assert columns(loaded) == columns(league)
+assert json.loads(loaded.to_json()) == json.loads(league.to_json())
+"#;
+    run_python("test_python_pickle", script);
+}
+
+#[test]
+fn test_python_record_games() {
+    let script = r#"
+from array import array
+
+def system():
+    system = belo.EloSystem()
+    for id in ("alice", "bob", "carol"):
+        system.add_player(id)
+    return system
+
+# Strings and ints mix, and every sequence type is accepted
+batch = system()
+deltas1, deltas2 = batch.record_games(["alice", "bob", "carol"], ["gt", -1, 0], ("bob", "carol", "alice"))
+assert len(deltas1) == len(deltas2) == 3
+assert deltas1[0] > 0 > deltas2[0] and deltas1[1] < 0 < deltas2[1]
+assert list(batch.leaderboard()["games"]) == [2, 2, 2]
+ints = system()
+ints.record_games(["alice"], array("q", [1]), ["bob"])
+assert ints.info("alice")["wins"] == 1
+
+# Recording one game at a time gives the same ratings
+single = system()
+for game in (("alice", "gt", "bob"), ("bob", "lt", "carol"), ("carol", "eq", "alice")):
+    single.record_game(*game)
+assert single.leaderboard()["rating"] == batch.leaderboard()["rating"]
+
+try:
+    import numpy
+except ImportError:
+    numpy = None
+if numpy is not None:
+    arrays = system()
+    deltas1, deltas2 = arrays.record_games(numpy.array(["alice"]), numpy.array([1]), numpy.array(["bob"]))
+    assert isinstance(deltas1, numpy.ndarray) and deltas1.dtype == numpy.float64
+    assert arrays.info("alice")["wins"] == 1
+else:
+    assert isinstance(deltas1, array) and deltas1.typecode == "d"
+
+# Bad input records nothing
+rejected = system()
+for results in ([True], [2], [1.5], ["won"]):
+    raises(ValueError, rejected.record_games, ["alice"], results, ["bob"])
+message = raises(ValueError, rejected.record_games, ["alice", "bob"], [1], ["bob"])
+assert "same length" in message, message
+raises(belo.UnknownPlayerError, rejected.record_games, ["alice", "alice"], [1, 1], ["bob", "dave"])
+assert list(rejected.leaderboard()["games"]) == [0, 0, 0]
+
+# The shared leaderboard takes the same input
+belo.init_state()
+belo.add_player("alice")
+belo.add_player("bob")
+deltas1, deltas2 = belo.record_games(["alice"], [1], ["bob"])
+assert deltas1[0] == -deltas2[0] > 0
+"#;
+    run_python("test_python_record_games", script);
+}
+
+#[test]
+fn test_python_leaderboard_columns() {
+    let script = r#"
+import math, sys
+from array import array
+
+KEYS = [
+    "rank", "id", "rating", "deviation", "volatility", "ci_lower", "ci_upper", "conservative",
+    "wins", "losses", "ties", "games", "points_for", "points_against", "last_played",
+]
+
+def check(numpy):
+    float_type = numpy.ndarray if numpy else array
+
+    empty = belo.EloSystem()
+    columns = empty.leaderboard()
+    assert list(columns) == KEYS
+    assert all(len(column) == 0 for column in columns.values())
+    assert isinstance(columns["rating"], float_type)
+    matrix = empty.pairwise_win_probabilities()
+    assert matrix.shape == (0, 0) if numpy else matrix == []
+
+    system = belo.EloSystem()
+    for id in ("alice", "bob", "carol"):
+        system.add_player(id)
+    system.record_game("alice", "gt", "bob")
+    system.record_game("bob", "gt", "carol")
+    system.record_game("alice", "3-1", "carol")
+
+    # Columns hold what `top` returns row by row, with "rating" for its "elo"
+    columns = system.leaderboard()
+    assert list(columns) == KEYS
+    assert list(columns["id"]) == ["alice", "bob", "carol"]
+    for key in ("rating", "deviation", "volatility", "ci_lower", "ci_upper", "conservative"):
+        assert isinstance(columns[key], float_type), key
+    for i, player in enumerate(system.top(3)):
+        for key in KEYS:
+            assert columns[key][i] == player["elo" if key == "rating" else key], key
+
+    # Rows and columns follow the leaderboard order for the same sort
+    for sort in ("rating", "conservative"):
+        ids = list(system.leaderboard(sort)["id"])
+        matrix = system.pairwise_win_probabilities(sort)
+        if numpy:
+            assert matrix.shape == (3, 3) and matrix.dtype == numpy.float64
+        else:
+            assert isinstance(matrix, list) and all(isinstance(row, array) for row in matrix)
+        for i in range(3):
+            assert math.isnan(matrix[i][i])
+            for j in range(3):
+                if i != j:
+                    assert math.isclose(matrix[i][j], system.predict(ids[i], ids[j])["win"])
+                    assert math.isclose(matrix[i][j] + matrix[j][i], 1.0)
+
+    belo.init_state()
+    belo.add_player("alice")
+    belo.add_player("bob")
+    belo.record_game("alice", "gt", "bob")
+    assert list(belo.leaderboard()["id"]) == ["alice", "bob"]
+    assert belo.pairwise_win_probabilities()[0][1] > 0.5
+
+try:
+    import numpy
+except ImportError:
+    numpy = None
+if numpy is not None:
+    check(numpy)
+# Without NumPy, the same data comes as `array.array`
+sys.modules["numpy"] = None
+check(None)
+"#;
+    run_python("test_python_leaderboard_columns", script);
+}